@@ -0,0 +1,103 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use aon::{deserialize, deserialize_borrowed, lexer, parse_events, parser};
+
+// Counts live heap bytes, to report the peak memory a parse takes next to
+// the timings criterion measures.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// The peak heap usage of `f` above what was allocated before it ran.
+fn peak_memory<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    black_box(f());
+    PEAK.load(Ordering::Relaxed) - before
+}
+
+// An array of `records` structs mixing every kind of value, around 180
+// bytes each.
+fn document(records: usize) -> String {
+    let mut aon = String::from("[\n");
+
+    for index in 0..records {
+        aon.push_str(&format!(
+            "    {{ id: {}, name: \"record {}\", score: -{}.5e2, active: {}, tags: [\"a\", \"b\"], // a comment\n      owner.name: null, kind: #some({}), shape: #circle {{ radius: {} }} }},\n",
+            index, index, index, index % 2 == 0, index, index % 10,
+        ));
+    }
+
+    aon.push(']');
+    aon
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+
+    for records in [10_000, 40_000] {
+        let aon = document(records);
+        let megabytes = aon.len() as f64 / 1_000_000.0;
+
+        // Streaming only holds the token being looked at, materializing the
+        // token vector first holds all of them next to the value tree.
+        let streaming = peak_memory(|| deserialize(&aon));
+        let borrowed = peak_memory(|| deserialize_borrowed(&aon));
+        let materialized = peak_memory(|| parser::parse_tokens(&lexer::tokenize(&aon).unwrap()));
+        let events = peak_memory(|| parse_events(&aon).count());
+        println!(
+            "{:.1} MB document: peak memory {:.1} MB events, {:.1} MB streaming, {:.1} MB borrowed, {:.1} MB materialized",
+            megabytes,
+            events as f64 / 1_000_000.0,
+            streaming as f64 / 1_000_000.0,
+            borrowed as f64 / 1_000_000.0,
+            materialized as f64 / 1_000_000.0,
+        );
+
+        group.throughput(Throughput::Bytes(aon.len() as u64));
+        group.bench_with_input(BenchmarkId::new("streaming", records), &aon, |b, aon| {
+            b.iter(|| deserialize(black_box(aon)))
+        });
+        group.bench_with_input(BenchmarkId::new("borrowed", records), &aon, |b, aon| {
+            b.iter(|| deserialize_borrowed(black_box(aon)))
+        });
+        group.bench_with_input(BenchmarkId::new("materialized", records), &aon, |b, aon| {
+            b.iter(|| parser::parse_tokens(&lexer::tokenize(black_box(aon)).unwrap()))
+        });
+        group.bench_with_input(BenchmarkId::new("events", records), &aon, |b, aon| {
+            b.iter(|| parse_events(black_box(aon)).count())
+        });
+        group.bench_with_input(BenchmarkId::new("lexer", records), &aon, |b, aon| {
+            b.iter(|| lexer::Lexer::new(black_box(aon)).count())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
@@ -0,0 +1,541 @@
+use std::{fmt::{self, Display}, iter::Peekable, mem, vec};
+
+use crate::error::{AonError, Result};
+
+use super::{lexer, options::ParseOptions, parser::{self, NodeKind, Sink}, tokens::{self, Token, TokenKind}};
+
+// A lossless concrete syntax tree. Unlike `Value` it keeps everything the
+// source was written with, spacing, comments, commas, quoting and dotted
+// keys, so printing it gives back the input byte for byte.
+//
+// Whatever is not part of the grammar is trivia attached to a neighbouring
+// token. Trivia on the same line following a token is its trailing trivia,
+// the rest leads the next token. Commas count as trivia too, a separating
+// comma trails the last token of the value before it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxTree {
+    pub value: Node,
+    // Holds the trivia following the value.
+    pub eof: SyntaxToken,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Trivia {
+    Whitespace(String),
+    // The comment as written, delimiters included.
+    Comment(String),
+    Comma,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxToken {
+    pub leading: Vec<Trivia>,
+    pub kind: TokenKind<'static>,
+    // The token as written, e.g. `'a'` rather than `"a"`.
+    pub text: String,
+    pub trailing: Vec<Trivia>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Node {
+    // A string, number, bool or null.
+    Scalar(SyntaxToken),
+    Struct(StructNode),
+    Union(Box<UnionNode>),
+    Array(ArrayNode),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct StructNode {
+    pub open: SyntaxToken,
+    pub entries: Vec<EntryNode>,
+    pub close: SyntaxToken,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct EntryNode {
+    // The names of a dotted key along with the dots between them.
+    pub key: Vec<SyntaxToken>,
+    pub colon: SyntaxToken,
+    pub value: Node,
+}
+
+// Arrays, and tuple payloads with parentheses in place of brackets.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ArrayNode {
+    pub open: SyntaxToken,
+    pub values: Vec<Node>,
+    pub close: SyntaxToken,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnionNode {
+    pub hash: SyntaxToken,
+    pub variant: SyntaxToken,
+    pub payload: PayloadNode,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PayloadNode {
+    Unit,
+    Struct(StructNode),
+    Tuple(ArrayNode),
+}
+
+impl SyntaxTree {
+    // Accepts exactly what `deserialize` accepts and fails with the same
+    // errors.
+    pub fn parse(aon: &str) -> Result<SyntaxTree> {
+        SyntaxTree::parse_with(aon, &ParseOptions::default())
+    }
+
+    // Accepts exactly what `deserialize_with` accepts with the same options.
+    pub fn parse_with(aon: &str, options: &ParseOptions) -> Result<SyntaxTree> {
+        let mut builder = Builder::default();
+        parser::parse_into(lexer::Lexer::new(aon).with_options(options), options, &mut builder)?;
+
+        // The parser only accepts what the builder can make a tree of.
+        builder.build(aon).ok_or_else(|| AonError::Custom("the syntax tree does not match the input".to_owned()))
+    }
+}
+
+impl SyntaxToken {
+    // The name a key segment or union variant stands for, with quotes and
+    // escapes resolved.
+    pub fn name(&self) -> Option<String> {
+        match &self.kind {
+            TokenKind::Word(name) | TokenKind::Ident(name) => Some(name.to_string()),
+            TokenKind::Null | TokenKind::Bool(_) => Some(self.text.to_owned()),
+            _ => None,
+        }
+    }
+}
+
+impl EntryNode {
+    pub fn path(&self) -> Vec<String> {
+        self.key.iter().filter_map(SyntaxToken::name).collect()
+    }
+}
+
+// Values and struct entries are both items of a comma separated list. The
+// trivia around an item lives on the tokens at either end of it.
+pub(crate) trait Item {
+    fn first_token_mut(&mut self) -> &mut SyntaxToken;
+    // The token a separating comma is attached to.
+    fn last_token_mut(&mut self) -> &mut SyntaxToken;
+}
+
+impl Item for Node {
+    fn first_token_mut(&mut self) -> &mut SyntaxToken {
+        match self {
+            Node::Scalar(token) => token,
+            Node::Struct(node) => &mut node.open,
+            Node::Union(node) => &mut node.hash,
+            Node::Array(node) => &mut node.open,
+        }
+    }
+
+    fn last_token_mut(&mut self) -> &mut SyntaxToken {
+        match self {
+            Node::Scalar(token) => token,
+            Node::Struct(node) => &mut node.close,
+            Node::Union(node) => match &mut node.payload {
+                PayloadNode::Unit => &mut node.variant,
+                PayloadNode::Struct(payload) => &mut payload.close,
+                PayloadNode::Tuple(payload) => &mut payload.close,
+            },
+            Node::Array(node) => &mut node.close,
+        }
+    }
+}
+
+impl Item for EntryNode {
+    fn first_token_mut(&mut self) -> &mut SyntaxToken {
+        &mut self.key[0]
+    }
+
+    fn last_token_mut(&mut self) -> &mut SyntaxToken {
+        self.value.last_token_mut()
+    }
+}
+
+// The structure the parser reports, before the tokens are given their trivia.
+enum Child {
+    Token,
+    Node(NodeKind, Vec<Child>),
+}
+
+// Follows `Parser` through the input to find out which tokens make up which
+// nodes, so that the tree is built with the same grammar, limits and checks
+// as the value is.
+#[derive(Default)]
+struct Builder<'a> {
+    // Every token read, comments included, the last one is EOF.
+    tokens: Vec<Token<'a>>,
+    // The value and EOF.
+    root: Vec<Child>,
+    // The nodes started and not finished yet, with their children so far.
+    open: Vec<(NodeKind, Vec<Child>)>,
+}
+
+impl<'a> Sink<'a> for Builder<'a> {
+    fn read(&mut self, token: &Token<'a>) {
+        self.tokens.push(token.clone());
+    }
+
+    fn take(&mut self) {
+        self.children().push(Child::Token);
+    }
+
+    fn start(&mut self, kind: NodeKind) {
+        self.open.push((kind, vec![]));
+    }
+
+    fn finish(&mut self) {
+        if let Some((kind, children)) = self.open.pop() {
+            self.children().push(Child::Node(kind, children));
+        }
+    }
+}
+
+impl Builder<'_> {
+    fn children(&mut self) -> &mut Vec<Child> {
+        match self.open.last_mut() {
+            Some((_, children)) => children,
+            None => &mut self.root,
+        }
+    }
+
+    // Builds the tree once the parser is done with the input. Each token
+    // gets the trivia around it, then takes its place in the nodes in the
+    // order the parser took it.
+    fn build(self, aon: &str) -> Option<SyntaxTree> {
+        let mut tokens = with_trivia(aon, self.tokens).into_iter().peekable();
+        let mut root = self.root.into_iter();
+        let value = node(root.next()?, &mut tokens)?;
+        let eof = tokens.next()?;
+
+        Some(SyntaxTree { value, eof })
+    }
+}
+
+type Tokens = Peekable<vec::IntoIter<SyntaxToken>>;
+
+// Pairs each token that is not a comment with the trivia before it, and the
+// trivia after it on the same line.
+fn with_trivia(aon: &str, tokens: Vec<Token>) -> Vec<SyntaxToken> {
+    let mut grouped = vec![];
+    let mut trivia = vec![];
+    let mut offset = 0;
+
+    // Whitespace is the only thing the lexer skips, so it is whatever lies
+    // between two tokens.
+    for token in tokens {
+        let (start, end) = (token.span.start.offset, token.span.end.offset);
+
+        if start > offset {
+            trivia.push(Trivia::Whitespace(aon[offset..start].to_owned()));
+        }
+
+        let text = aon[start..end].to_owned();
+        offset = end;
+
+        match token.kind {
+            TokenKind::Comment(_) | TokenKind::BlockComment(_) => trivia.push(Trivia::Comment(text)),
+            kind => grouped.push(SyntaxToken { leading: mem::take(&mut trivia), kind: kind.into_owned(), text, trailing: vec![] }),
+        }
+    }
+
+    for index in 1..grouped.len() {
+        let trailing = trailing_trivia(&mut grouped[index].leading);
+        grouped[index - 1].trailing = trailing;
+    }
+
+    grouped
+}
+
+// Takes the trivia before a token up to the end of the line the one before
+// it is on, splitting whitespace that runs into the next line.
+fn trailing_trivia(leading: &mut Vec<Trivia>) -> Vec<Trivia> {
+    let mut count = 0;
+    let mut split = None;
+
+    for piece in leading.iter_mut() {
+        match piece {
+            Trivia::Whitespace(whitespace) => match whitespace.find(['\r', '\n']) {
+                Some(0) => break,
+                Some(index) => {
+                    split = Some(Trivia::Whitespace(whitespace[..index].to_owned()));
+                    whitespace.replace_range(..index, "");
+                    break;
+                }
+                None => {}
+            },
+            Trivia::Comment(comment) if comment.contains('\n') => break,
+            _ => {}
+        }
+
+        count += 1;
+    }
+
+    leading.drain(..count).chain(split).collect()
+}
+
+fn node(child: Child, tokens: &mut Tokens) -> Option<Node> {
+    match child {
+        Child::Token => tokens.next().map(Node::Scalar),
+        Child::Node(NodeKind::Struct, children) => struct_node(children, tokens).map(Node::Struct),
+        Child::Node(NodeKind::Array, children) => array_node(children, tokens).map(Node::Array),
+        Child::Node(NodeKind::Union, children) => union_node(children, tokens).map(|node| Node::Union(Box::new(node))),
+        Child::Node(NodeKind::Entry | NodeKind::Tuple, _) => None,
+    }
+}
+
+fn struct_node(children: Vec<Child>, tokens: &mut Tokens) -> Option<StructNode> {
+    let (open, entries, close) = delimited(children, tokens, |child, tokens| match child {
+        Child::Node(NodeKind::Entry, children) => entry_node(children, tokens),
+        _ => None,
+    })?;
+
+    Some(StructNode { open, entries, close })
+}
+
+fn array_node(children: Vec<Child>, tokens: &mut Tokens) -> Option<ArrayNode> {
+    let (open, values, close) = delimited(children, tokens, node)?;
+    Some(ArrayNode { open, values, close })
+}
+
+// The items between two delimiters. A separating comma, along with the
+// trivia around it, is attached to the token before it.
+fn delimited<T: Item>(children: Vec<Child>, tokens: &mut Tokens, item: impl Fn(Child, &mut Tokens) -> Option<T>) -> Option<(SyntaxToken, Vec<T>, SyntaxToken)> {
+    let mut children = children.into_iter();
+    let (Some(Child::Token), Some(Child::Token)) = (children.next(), children.next_back()) else {
+        return None;
+    };
+
+    let open = tokens.next()?;
+    let mut items: Vec<T> = vec![];
+
+    for child in children {
+        match (&child, tokens.peek()) {
+            (Child::Token, Some(SyntaxToken { kind: TokenKind::Comma, .. })) => {
+                let comma = tokens.next()?;
+                let last = items.last_mut()?.last_token_mut();
+                last.trailing.extend(comma.leading);
+                last.trailing.push(Trivia::Comma);
+                last.trailing.extend(comma.trailing);
+            }
+            _ => items.push(item(child, tokens)?),
+        }
+    }
+
+    Some((open, items, tokens.next()?))
+}
+
+fn entry_node(children: Vec<Child>, tokens: &mut Tokens) -> Option<EntryNode> {
+    let mut children = children.into_iter();
+    let value = children.next_back()?;
+    let mut key = vec![];
+
+    for _ in children {
+        key.push(tokens.next()?);
+    }
+
+    let colon = key.pop().filter(|colon| colon.kind == TokenKind::Colon)?;
+    let value = node(value, tokens)?;
+
+    Some(EntryNode { key, colon, value })
+}
+
+fn union_node(children: Vec<Child>, tokens: &mut Tokens) -> Option<UnionNode> {
+    let mut children = children.into_iter();
+    let (Some(Child::Token), Some(Child::Token)) = (children.next(), children.next()) else {
+        return None;
+    };
+
+    let (hash, variant) = (tokens.next()?, tokens.next()?);
+
+    let payload = match children.next() {
+        None => PayloadNode::Unit,
+        Some(Child::Node(NodeKind::Struct, children)) => PayloadNode::Struct(struct_node(children, tokens)?),
+        Some(Child::Node(NodeKind::Tuple, children)) => PayloadNode::Tuple(delimited(children, tokens, node).map(|(open, values, close)| ArrayNode { open, values, close })?),
+        Some(_) => return None,
+    };
+
+    Some(UnionNode { hash, variant, payload })
+}
+
+impl Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.value, self.eof)
+    }
+}
+
+impl Display for Trivia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trivia::Whitespace(text) | Trivia::Comment(text) => write!(f, "{}", text),
+            Trivia::Comma => write!(f, "{}", tokens::COMMA),
+        }
+    }
+}
+
+impl Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.leading.iter().try_for_each(|trivia| write!(f, "{}", trivia))?;
+        write!(f, "{}", self.text)?;
+        self.trailing.iter().try_for_each(|trivia| write!(f, "{}", trivia))
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Scalar(token) => write!(f, "{}", token),
+            Node::Struct(node) => write!(f, "{}", node),
+            Node::Union(node) => write!(f, "{}", node),
+            Node::Array(node) => write!(f, "{}", node),
+        }
+    }
+}
+
+impl Display for StructNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.open)?;
+        self.entries.iter().try_for_each(|entry| write!(f, "{}", entry))?;
+        write!(f, "{}", self.close)
+    }
+}
+
+impl Display for EntryNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.key.iter().try_for_each(|token| write!(f, "{}", token))?;
+        write!(f, "{}{}", self.colon, self.value)
+    }
+}
+
+impl Display for ArrayNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.open)?;
+        self.values.iter().try_for_each(|value| write!(f, "{}", value))?;
+        write!(f, "{}", self.close)
+    }
+}
+
+impl Display for UnionNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.hash, self.variant)?;
+
+        match &self.payload {
+            PayloadNode::Unit => Ok(()),
+            PayloadNode::Struct(node) => write!(f, "{}", node),
+            PayloadNode::Tuple(node) => write!(f, "{}", node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deserializer::{deserialize, deserialize_with, options::Dialect, span::span};
+    use super::*;
+
+    fn whitespace(text: &str) -> Trivia {
+        Trivia::Whitespace(text.to_owned())
+    }
+
+    fn comment(text: &str) -> Trivia {
+        Trivia::Comment(text.to_owned())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sources = [
+            "null",
+            "  \n\t 1.5e3  \n",
+            "{}",
+            "// leading\n{ a: 1, b.c : \"two\" , } // trailing",
+            "{\r\n    name: \"server\", // the name\r\n    /* port */ port: 8080,\r\n}\r\n",
+            "[ 1 ,2,\n\n  3 /* three */\n  , ]",
+            "#none",
+            "#some ( 42 , )",
+            "#point(1, -2) /* a\n * block\n */",
+            "{\n  \"quoted key\": #\"odd variant\" {\n    x: [ ], // empty\n  },\n  esc: \"\\u{e9}\\n\",\n}",
+        ];
+
+        for source in sources {
+            assert_eq!(SyntaxTree::parse(source).map(|tree| tree.to_string()), Ok(source.to_owned()));
+        }
+
+        let lenient = ParseOptions::new().dialect(Dialect::Lenient);
+
+        for source in ["// leading\n{ a: 1, b.c : 'two' , } // trailing", "{ mode: fast, a./* b */b: 1 }"] {
+            assert_eq!(SyntaxTree::parse_with(source, &lenient).map(|tree| tree.to_string()), Ok(source.to_owned()));
+        }
+    }
+
+    #[test]
+    fn test_trivia() {
+        let tree = SyntaxTree::parse("[\n  1, // one\n  2\n  , 3 /* three */\n]\n").unwrap();
+
+        let Node::Array(array) = &tree.value else { panic!("expected an array") };
+        let [Node::Scalar(one), Node::Scalar(two), Node::Scalar(three)] = &array.values[..] else { panic!("expected three scalars") };
+
+        assert_eq!(array.open.trailing, vec![]);
+        assert_eq!(one.leading, vec![whitespace("\n  ")]);
+        assert_eq!(one.trailing, vec![Trivia::Comma, whitespace(" "), comment("// one")]);
+        assert_eq!(two.leading, vec![whitespace("\n  ")]);
+        assert_eq!(two.trailing, vec![whitespace("\n  "), Trivia::Comma, whitespace(" ")]);
+        assert_eq!(three.trailing, vec![whitespace(" "), comment("/* three */")]);
+        assert_eq!(array.close.leading, vec![whitespace("\n")]);
+        assert_eq!(tree.eof.leading, vec![whitespace("\n")]);
+    }
+
+    #[test]
+    fn test_keys() {
+        let tree = SyntaxTree::parse("{ a.\"b\".null: 1, \"c\": 2 }").unwrap();
+        let Node::Struct(node) = &tree.value else { panic!("expected a struct") };
+
+        assert_eq!(node.entries[0].path(), vec!["a", "b", "null"]);
+        assert_eq!(node.entries[0].key[2].text, "\"b\"");
+        assert_eq!(node.entries[1].path(), vec!["c"]);
+
+        let tree = SyntaxTree::parse_with("{ a.'b'.null: 1 }", &ParseOptions::new().dialect(Dialect::Lenient)).unwrap();
+        let Node::Struct(node) = &tree.value else { panic!("expected a struct") };
+
+        assert_eq!(node.entries[0].path(), vec!["a", "b", "null"]);
+        assert_eq!(node.entries[0].key[2].text, "'b'");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(SyntaxTree::parse("[1 2]"), Err(AonError::UnexpectedToken(
+            TokenKind::Number(crate::representation::value::Number::PosInt(2)),
+            span(3, 4),
+        )));
+        assert_eq!(SyntaxTree::parse("{ a: 1, a.b: 2 }"), Err(AonError::KeyCollision("a".to_owned(), span(8, 11))));
+        assert_eq!(SyntaxTree::parse("/* open"), Err(AonError::UnexpectedEndOfFile(span(7, 7))));
+
+        // Found while building the tree, the same as the parser finds them.
+        for aon in ["{ a.b: 1, a.b.c: 2 }", "{ \"a.b\": 1 }", "[nul]", "{ a: { b: 1 }, a.b.c: [2] }", "#x { a: [1 2] }", "{ a: 1 } 2"] {
+            assert_eq!(SyntaxTree::parse(aon).map(|_| ()), deserialize(aon).map(|_| ()));
+        }
+
+        // And the same in each dialect.
+        let cases = [
+            (Dialect::Strict, "{ a./* x */b: 1 }"),
+            (Dialect::Strict, "{ a /* x */.b: 1 }"),
+            (Dialect::Strict, "#/* x */some(1)"),
+            (Dialect::Strict, "{ mode: fast }"),
+            (Dialect::Lenient, "{ mode: fast, 'a b': 'it\\'s', a./* x */b: 1 }"),
+            (Dialect::Json, r#"{ "a.b": 1, "c": [true, null] }"#),
+            (Dialect::Json, r#"{ a: 1 }"#),
+            (Dialect::Json, "[1, 2, ]"),
+            (Dialect::Json, r#"{ "a": 1, }"#),
+        ];
+
+        for (dialect, aon) in cases {
+            let options = ParseOptions::new().dialect(dialect);
+            assert_eq!(SyntaxTree::parse_with(aon, &options).map(|_| ()), deserialize_with(aon, &options).map(|_| ()), "{}", aon);
+        }
+    }
+}
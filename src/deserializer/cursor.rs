@@ -1,51 +1,167 @@
-use std::str::Chars;
+use std::{borrow::Cow, io::Read, str::Chars};
 
 use crate::AonError;
 
+use super::{reader::ReadChars, span::{Position, Span}};
+
 pub(crate) struct Cursor<'a> {
-    pub aon: Chars<'a>,
+    source: Source<'a>,
+    position: Position,
+    // The input is cut short at `limit` bytes, which is an error once the
+    // cursor gets there if it was any longer.
+    limit: Option<usize>,
+    truncated: bool,
+}
+
+enum Source<'a> {
+    Str(Chars<'a>),
+    // Text read from a stream cannot be borrowed, so while something is
+    // being captured the chars are collected as they are consumed.
+    Reader(ReadChars<'a>, Option<String>),
 }
 
+// Where the text returned by `Cursor::since` starts.
+pub(crate) struct Mark<'a>(&'a str);
+
 pub(crate) const EOF: char = '\0';
 
 impl<'a> Cursor<'a> {
-    pub(crate) fn new(aon: &'a str) -> Cursor<'a> {
+    // A cursor over `aon`, which starts at `position` in the text it was
+    // taken from.
+    pub(crate) fn starting_at(aon: &'a str, position: Position) -> Cursor<'a> {
         Cursor {
-            aon: aon.chars(),
+            source: Source::Str(aon.chars()),
+            position,
+            limit: None,
+            truncated: false,
+        }
+    }
+
+    pub(crate) fn from_reader(reader: impl Read + 'a) -> Cursor<'a> {
+        Cursor {
+            source: Source::Reader(ReadChars::new(reader), None),
+            position: Position::START,
+            limit: None,
+            truncated: false,
+        }
+    }
+
+    pub(crate) fn limit(&mut self, limit: usize) {
+        self.limit = Some(limit);
+
+        match &mut self.source {
+            Source::Str(chars) => {
+                let rest = chars.as_str();
+                let mut end = limit.saturating_sub(self.position.offset);
+
+                if end < rest.len() {
+                    while !rest.is_char_boundary(end) {
+                        end -= 1;
+                    }
+
+                    *chars = rest[..end].chars();
+                    self.truncated = true;
+                }
+            }
+            Source::Reader(chars, _) => chars.limit(limit.saturating_sub(self.position.offset)),
         }
     }
 
     pub(crate) fn first(&mut self) -> char {
-        self.aon.clone().next().unwrap_or(EOF)
+        self.peek(0).unwrap_or(EOF)
     }
 
     pub(crate) fn second(&mut self) -> char {
-        let mut clone = self.aon.clone();
-        clone.next();
-        clone.next().unwrap_or(EOF)
+        self.peek(1).unwrap_or(EOF)
     }
 
     pub(crate) fn is_eof(&mut self) -> bool {
-        self.aon.as_str().is_empty()
+        self.peek(0).is_none()
+    }
+
+    fn peek(&mut self, index: usize) -> Option<char> {
+        match &mut self.source {
+            Source::Str(chars) => chars.clone().nth(index),
+            Source::Reader(chars, _) => chars.peek(index),
+        }
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        self.position
     }
 
     pub(crate) fn bump(&mut self) -> Option<char> {
-        self.aon.next()
+        let c = match &mut self.source {
+            Source::Str(chars) => chars.next()?,
+            Source::Reader(chars, capture) => {
+                let c = chars.next()?;
+                capture.iter_mut().for_each(|capture| capture.push(c));
+                c
+            }
+        };
+
+        self.position = self.position.advance(c);
+        Some(c)
     }
 
     pub(crate) fn eat_while(&mut self, mut predicate: impl FnMut(char) -> bool) {
-        while predicate(self.first()) && !self.is_eof() {
+        while !self.is_eof() && predicate(self.first()) {
             self.bump();
         }
     }
 
-    pub(crate) fn eat_text(&mut self, expected_text: &str) -> Result<(), AonError> {
-        for c in expected_text.chars() {
-            if self.first() != c {
-                return Err(AonError::UnexpectedCharacter(self.first()))
+    // Starts capturing the text consumed from here on.
+    pub(crate) fn mark(&mut self) -> Mark<'a> {
+        match &mut self.source {
+            Source::Str(chars) => Mark(chars.as_str()),
+            Source::Reader(_, capture) => {
+                *capture = Some(String::new());
+                Mark("")
             }
         }
+    }
+
+    // The text consumed since `mark`, borrowed from the input if it can be.
+    pub(crate) fn since(&mut self, mark: Mark<'a>) -> Cow<'a, str> {
+        match &mut self.source {
+            Source::Str(chars) => Cow::Borrowed(&mark.0[..mark.0.len() - chars.as_str().len()]),
+            Source::Reader(_, capture) => Cow::Owned(capture.take().unwrap_or_default()),
+        }
+    }
+
+    // An error reading the input or the input going past the limit, either
+    // of which cuts it short wherever it occurs.
+    pub(crate) fn take_error(&mut self) -> Option<AonError> {
+        let error = match &mut self.source {
+            Source::Str(_) => None,
+            Source::Reader(chars, _) => chars.take_error().map(AonError::from),
+        };
 
-        Ok(())
+        if error.is_some() {
+            return error;
+        }
+
+        let limit = self.limit?;
+        let truncated = match &self.source {
+            Source::Str(_) => self.truncated,
+            Source::Reader(chars, _) => chars.truncated(),
+        };
+
+        match truncated && self.is_eof() {
+            true => {
+                self.limit = None;
+                Some(AonError::SizeLimitExceeded(limit, Span::new(self.position, self.position)))
+            }
+            false => None,
+        }
+    }
+
+    // An error pointing at the char under the cursor, or at the end of the
+    // input if there is none.
+    pub(crate) fn unexpected_character(&mut self) -> AonError {
+        match self.is_eof() {
+            true => AonError::UnexpectedEndOfFile(Span::new(self.position, self.position)),
+            false => AonError::UnexpectedCharacter(self.first(), Span::char(self.position, self.first())),
+        }
     }
 }
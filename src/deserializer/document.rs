@@ -0,0 +1,692 @@
+use std::{fmt::{self, Display}, mem};
+
+use serde::Serialize;
+
+use crate::{
+    representation::Value,
+    serializer::{self, formatter::{FormatBuilder, Formatter}, ToAon},
+    error::{AonError, Result},
+};
+
+use super::{cst::{EntryNode, Item, Node, PayloadNode, StructNode, ArrayNode, SyntaxToken, SyntaxTree, Trivia}, deserialize_with, options::{Dialect, ParseOptions}, tokens::{self, TokenKind}};
+
+// An AON document that can be edited in place. Edits only touch the part of
+// the source they change, comments, spacing and key order elsewhere are
+// printed back as they were read.
+//
+// Values are addressed by dotted paths, `a.b.c`, where a segment is a key in
+// a struct or an index in an array or tuple. Unions are looked through to
+// their payload, and the empty path is the whole document.
+//
+// New values are spaced the way the rest of the document is.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Document {
+    tree: SyntaxTree,
+    layout: Layout,
+    options: ParseOptions,
+}
+
+impl Document {
+    pub fn parse(aon: &str) -> Result<Document> {
+        Document::parse_with(aon, &ParseOptions::default())
+    }
+
+    // Parses `aon` within the limits set in `options`, which `value` reads
+    // the document back with too.
+    pub fn parse_with(aon: &str, options: &ParseOptions) -> Result<Document> {
+        let tree = SyntaxTree::parse_with(aon, options)?;
+        let layout = Layout::of(&tree.value);
+        Ok(Document { tree, layout, options: options.clone() })
+    }
+
+    pub fn value(&self) -> Result<Value> {
+        deserialize_with(&self.tree.to_string(), &self.options)
+    }
+
+    // Replaces the value at `path`, or adds it when it is missing. Missing
+    // keys are added to the innermost existing struct, in dot notation if
+    // more than one is missing, as nested structs in JSON.
+    pub fn set<T: ?Sized + Serialize>(&mut self, path: &str, value: &T) -> Result<()> {
+        let value = serializer::to_value(value)?;
+        let path = segments(path);
+
+        if path.is_empty() {
+            replace_node(&mut self.tree.value, node(&value, &self.layout, &self.options)?);
+            return Ok(());
+        }
+
+        match resolve(&mut self.tree.value, &path)? {
+            Parent::Struct(node, key) => {
+                let matches = matching_entries(node, &key);
+
+                match matches.split_first() {
+                    Some((&first, _)) if node.entries[first].path() == key => {
+                        replace_node(&mut node.entries[first].value, self::node(&value, &self.layout, &self.options)?);
+                    }
+                    // The key only exists through longer dotted keys, which
+                    // are all replaced by one entry in place of the first.
+                    Some((&first, rest)) => {
+                        rest.iter().rev().for_each(|&index| { remove_item(&mut node.entries, index); });
+
+                        let old = &mut node.entries[first];
+                        let mut new = entry(&key, &value, &self.layout, &self.options)?;
+                        new.first_token_mut().leading = mem::take(&mut old.first_token_mut().leading);
+                        new.last_token_mut().trailing = mem::take(&mut old.last_token_mut().trailing);
+                        *old = new;
+                    }
+                    None => {
+                        let index = node.entries.len();
+                        insert_item(&mut node.entries, index, entry(&key, &value, &self.layout, &self.options)?, &node.open, &node.close, &self.layout);
+                    }
+                }
+            }
+            Parent::List(node, index) => match index < node.values.len() {
+                true => replace_node(&mut node.values[index], self::node(&value, &self.layout, &self.options)?),
+                false => {
+                    check_index(node, index, &path)?;
+                    insert_item(&mut node.values, index, self::node(&value, &self.layout, &self.options)?, &node.open, &node.close, &self.layout);
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    // Adds a key to a struct, or a value to an array at the given index,
+    // moving the ones after it back. Fails when the key already exists.
+    pub fn insert<T: ?Sized + Serialize>(&mut self, path: &str, value: &T) -> Result<()> {
+        let value = serializer::to_value(value)?;
+        let path = segments(path);
+
+        match resolve(&mut self.tree.value, &path)? {
+            Parent::Struct(node, key) => {
+                if !matching_entries(node, &key).is_empty() {
+                    return Err(AonError::PathExists(path.join(".")));
+                }
+
+                let index = node.entries.len();
+                insert_item(&mut node.entries, index, entry(&key, &value, &self.layout, &self.options)?, &node.open, &node.close, &self.layout);
+            }
+            Parent::List(node, index) => {
+                check_index(node, index, &path)?;
+                insert_item(&mut node.values, index, self::node(&value, &self.layout, &self.options)?, &node.open, &node.close, &self.layout);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Removes a key from a struct or a value from an array, along with the
+    // comments leading up to it.
+    pub fn remove(&mut self, path: &str) -> Result<()> {
+        let path = segments(path);
+
+        match resolve(&mut self.tree.value, &path)? {
+            Parent::Struct(node, key) => {
+                let matches = matching_entries(node, &key);
+
+                if matches.is_empty() {
+                    return Err(not_found(&path));
+                }
+
+                matches.into_iter().rev().for_each(|index| { remove_item(&mut node.entries, index); });
+            }
+            Parent::List(node, index) => match index < node.values.len() {
+                true => { remove_item(&mut node.values, index); }
+                false => return Err(not_found(&path)),
+            },
+        }
+
+        Ok(())
+    }
+
+    // Renames the variant of the union at `path`, keeping its payload.
+    pub fn set_variant(&mut self, path: &str, variant: &str) -> Result<()> {
+        let path = segments(path);
+
+        let node = match path.is_empty() {
+            true => &mut self.tree.value,
+            false => match resolve(&mut self.tree.value, &path)? {
+                Parent::Struct(node, key) => match matching_entries(node, &key).last() {
+                    Some(&index) if node.entries[index].path() == key => &mut node.entries[index].value,
+                    _ => return Err(not_found(&path)),
+                },
+                Parent::List(node, index) => node.values.get_mut(index).ok_or_else(|| not_found(&path))?,
+            },
+        };
+
+        match node {
+            Node::Union(union) => {
+                let Node::Union(new) = parse(&format!("#{}", serializer::format_name(&variant.to_owned(), &formatter(&self.options))), &self.options)? else {
+                    unreachable!("a hash starts a union")
+                };
+
+                union.variant.kind = new.variant.kind;
+                union.variant.text = new.variant.text;
+                Ok(())
+            }
+            // JSON writes a union as an object with its variant under `type`.
+            Node::Struct(node) if self.options.dialect == Dialect::Json => {
+                let index = node.entries.iter().position(|entry| entry.path() == [tokens::UNION_TYPE]);
+
+                match index.map(|index| &mut node.entries[index].value) {
+                    Some(old @ Node::Scalar(SyntaxToken { kind: TokenKind::Word(_), .. })) => {
+                        replace_node(old, self::node(&Value::String(variant.to_owned()), &self.layout, &self.options)?);
+                        Ok(())
+                    }
+                    _ => Err(AonError::NotAUnion(path.join("."))),
+                }
+            }
+            _ => Err(AonError::NotAUnion(path.join("."))),
+        }
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.tree)
+    }
+}
+
+fn segments(path: &str) -> Vec<String> {
+    match path.is_empty() {
+        true => vec![],
+        false => path.split(tokens::DOT).map(str::to_owned).collect(),
+    }
+}
+
+fn resolve<'a>(node: &'a mut Node, path: &[String]) -> Result<Parent<'a>> {
+    match path.is_empty() {
+        true => Err(AonError::InvalidPath(String::new())),
+        false => parent(node, path).ok_or_else(|| not_found(path)),
+    }
+}
+
+fn not_found(path: &[String]) -> AonError {
+    AonError::PathNotFound(path.join("."))
+}
+
+// The container an edit at a path applies to. For a struct this is the
+// innermost one written out, along with the rest of the path, which may be
+// spread over dotted keys. For a list it is the index.
+enum Parent<'a> {
+    Struct(&'a mut StructNode, Vec<String>),
+    List(&'a mut ArrayNode, usize),
+}
+
+// Finds the container `path` ends in, if there is one.
+fn parent<'a>(node: &'a mut Node, path: &[String]) -> Option<Parent<'a>> {
+    match node {
+        Node::Struct(node) => struct_parent(node, path),
+        Node::Array(node) => list_parent(node, path),
+        Node::Union(union) => match &mut union.payload {
+            PayloadNode::Struct(node) => struct_parent(node, path),
+            PayloadNode::Tuple(node) => list_parent(node, path),
+            PayloadNode::Unit => None,
+        },
+        Node::Scalar(_) => None,
+    }
+}
+
+fn struct_parent<'a>(node: &'a mut StructNode, path: &[String]) -> Option<Parent<'a>> {
+    // An entry whose key leads part of the way is descended into, the
+    // parser lets later entries win so the search goes from the back.
+    let entry = node.entries.iter().rposition(|entry| {
+        let key = entry.path();
+        key.len() < path.len() && path.starts_with(&key)
+    });
+
+    match entry {
+        Some(index) => {
+            let depth = node.entries[index].path().len();
+            parent(&mut node.entries[index].value, &path[depth..])
+        }
+        None => Some(Parent::Struct(node, path.to_vec())),
+    }
+}
+
+fn list_parent<'a>(node: &'a mut ArrayNode, path: &[String]) -> Option<Parent<'a>> {
+    let index = path[0].parse::<usize>().ok()?;
+
+    match path.len() {
+        1 => Some(Parent::List(node, index)),
+        _ => parent(node.values.get_mut(index)?, &path[1..]),
+    }
+}
+
+// The entries that hold `key` or, through dotted keys, part of it.
+fn matching_entries(node: &StructNode, key: &[String]) -> Vec<usize> {
+    node.entries.iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.path().starts_with(key))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn check_index(node: &ArrayNode, index: usize, path: &[String]) -> Result<()> {
+    match index <= node.values.len() {
+        true => Ok(()),
+        false => Err(AonError::IndexOutOfBounds(path.join("."), node.values.len())),
+    }
+}
+
+// New values are written in the dialect of the document, so that it still
+// parses with the same options.
+fn parse(aon: &str, options: &ParseOptions) -> Result<Node> {
+    Ok(SyntaxTree::parse_with(aon, options)?.value)
+}
+
+fn formatter(options: &ParseOptions) -> Formatter {
+    match options.dialect {
+        Dialect::Json => FormatBuilder::new().json_compatible_unions().dot_notation(false).build(),
+        _ => Formatter::default(),
+    }
+}
+
+// The serializer writes values without any spacing, which is added to match
+// the document.
+fn node(value: &Value, layout: &Layout, options: &ParseOptions) -> Result<Node> {
+    let mut node = parse(&value.to_aon(&formatter(options)), options)?;
+    layout.apply(&mut node);
+    Ok(node)
+}
+
+fn entry(key: &[String], value: &Value, layout: &Layout, options: &ParseOptions) -> Result<EntryNode> {
+    let formatter = formatter(options);
+
+    // JSON has no dotted keys, the structs they stand for are written out.
+    let (key, value) = match options.dialect {
+        Dialect::Json if key.len() > 1 => (&key[..1], key[1..].iter().rev().fold(value.clone(), |value, name| Value::Struct(vec![(name.to_owned(), value)]))),
+        _ => (key, value.clone()),
+    };
+
+    let key = key.iter().map(|name| serializer::format_name(name, &formatter)).collect::<Vec<_>>();
+
+    match parse(&format!("{{{}:{}}}", key.join("."), value.to_aon(&formatter)), options)? {
+        Node::Struct(mut node) => {
+            let mut entry = node.entries.remove(0);
+            layout.apply_entry(&mut entry);
+            Ok(entry)
+        }
+        _ => unreachable!("a brace starts a struct"),
+    }
+}
+
+// How a document spaces its tokens on a line: after colons, after commas and
+// inside the braces of a struct. Each is taken from the first place it shows,
+// a space where it does not.
+#[derive(Debug, PartialEq, Clone)]
+struct Layout {
+    colon: bool,
+    comma: bool,
+    braces: bool,
+}
+
+impl Layout {
+    fn of(node: &Node) -> Layout {
+        let mut tokens = vec![];
+        collect_tokens(node, &mut tokens);
+
+        let (mut colon, mut comma, mut braces) = (None, None, None);
+
+        for pair in tokens.windows(2) {
+            let (token, next) = (pair[0], pair[1]);
+            let gap = |trivia: &[Trivia]| gap(trivia.iter().chain(&next.leading));
+
+            match token.kind {
+                TokenKind::Colon => colon = colon.or(gap(&token.trailing)),
+                TokenKind::LeftBrace if next.kind != TokenKind::RightBrace => braces = braces.or(gap(&token.trailing)),
+                _ => {}
+            }
+
+            if let Some(index) = token.trailing.iter().position(|trivia| trivia == &Trivia::Comma) {
+                comma = comma.or(gap(&token.trailing[index + 1..]));
+            }
+        }
+
+        Layout {
+            colon: colon.unwrap_or(true),
+            comma: comma.unwrap_or(true),
+            braces: braces.unwrap_or(true),
+        }
+    }
+
+    // What follows a comma between items on the same line.
+    fn separator(&self) -> Vec<Trivia> {
+        self.comma.then(|| whitespace(" ")).into_iter().collect()
+    }
+
+    // Spaces a node written without any spacing.
+    fn apply(&self, node: &mut Node) {
+        match node {
+            Node::Scalar(token) => self.apply_token(token),
+            Node::Struct(node) => self.apply_struct(node),
+            Node::Array(node) => self.apply_array(node),
+            Node::Union(union) => match &mut union.payload {
+                PayloadNode::Unit => self.apply_token(&mut union.variant),
+                PayloadNode::Struct(node) => self.apply_struct(node),
+                PayloadNode::Tuple(node) => self.apply_array(node),
+            },
+        }
+    }
+
+    fn apply_struct(&self, node: &mut StructNode) {
+        node.entries.iter_mut().for_each(|entry| self.apply_entry(entry));
+
+        if self.braces && !node.entries.is_empty() {
+            node.open.trailing.push(whitespace(" "));
+            node.close.leading.push(whitespace(" "));
+        }
+
+        self.apply_token(&mut node.close);
+    }
+
+    fn apply_array(&self, node: &mut ArrayNode) {
+        node.values.iter_mut().for_each(|value| self.apply(value));
+        self.apply_token(&mut node.close);
+    }
+
+    fn apply_entry(&self, entry: &mut EntryNode) {
+        if self.colon {
+            entry.colon.trailing.push(whitespace(" "));
+        }
+
+        self.apply(&mut entry.value);
+    }
+
+    // Spaces the comma following a token, if there is one.
+    fn apply_token(&self, token: &mut SyntaxToken) {
+        if self.comma && token.trailing.last() == Some(&Trivia::Comma) {
+            token.trailing.push(whitespace(" "));
+        }
+    }
+}
+
+// Every token in `node`, in the order they are written.
+fn collect_tokens<'a>(node: &'a Node, tokens: &mut Vec<&'a SyntaxToken>) {
+    match node {
+        Node::Scalar(token) => tokens.push(token),
+        Node::Struct(node) => collect_struct(node, tokens),
+        Node::Array(node) => collect_array(node, tokens),
+        Node::Union(union) => {
+            tokens.push(&union.hash);
+            tokens.push(&union.variant);
+
+            match &union.payload {
+                PayloadNode::Unit => {}
+                PayloadNode::Struct(node) => collect_struct(node, tokens),
+                PayloadNode::Tuple(node) => collect_array(node, tokens),
+            }
+        }
+    }
+}
+
+fn collect_struct<'a>(node: &'a StructNode, tokens: &mut Vec<&'a SyntaxToken>) {
+    tokens.push(&node.open);
+
+    for entry in &node.entries {
+        tokens.extend(&entry.key);
+        tokens.push(&entry.colon);
+        collect_tokens(&entry.value, tokens);
+    }
+
+    tokens.push(&node.close);
+}
+
+fn collect_array<'a>(node: &'a ArrayNode, tokens: &mut Vec<&'a SyntaxToken>) {
+    tokens.push(&node.open);
+    node.values.iter().for_each(|value| collect_tokens(value, tokens));
+    tokens.push(&node.close);
+}
+
+// Whether there is a space between two tokens on the same line, or nothing
+// to tell by when they are on different ones or a comment is between them.
+fn gap<'a>(mut trivia: impl Iterator<Item = &'a Trivia>) -> Option<bool> {
+    match (trivia.next(), trivia.next()) {
+        (None, _) => Some(false),
+        (Some(Trivia::Whitespace(whitespace)), None) if !whitespace.contains('\n') => Some(true),
+        _ => None,
+    }
+}
+
+// Puts `new` in place of `old`, keeping the trivia around it.
+fn replace_node(old: &mut Node, mut new: Node) {
+    new.first_token_mut().leading = mem::take(&mut old.first_token_mut().leading);
+    new.last_token_mut().trailing = mem::take(&mut old.last_token_mut().trailing);
+    *old = new;
+}
+
+// The line break and indentation an item starts with, if it is on a line of
+// its own.
+fn indentation(token: &SyntaxToken) -> Option<String> {
+    match token.leading.last() {
+        Some(Trivia::Whitespace(whitespace)) if whitespace.contains('\n') => Some(whitespace.to_owned()),
+        _ => None,
+    }
+}
+
+fn whitespace(text: &str) -> Trivia {
+    Trivia::Whitespace(text.to_owned())
+}
+
+// Inserts an item into a comma separated list, laying it out like the items
+// around it: on a line of its own in a list spread over lines, separated by
+// a space otherwise.
+fn insert_item<T: Item>(items: &mut Vec<T>, index: usize, mut item: T, open: &SyntaxToken, close: &SyntaxToken, layout: &Layout) {
+    if let Some(next) = items.get_mut(index) {
+        let indentation = indentation(next.first_token_mut());
+
+        item.last_token_mut().trailing = match indentation {
+            Some(_) => vec![Trivia::Comma],
+            None => [vec![Trivia::Comma], layout.separator()].concat(),
+        };
+        item.first_token_mut().leading = indentation.map(Trivia::Whitespace).into_iter().collect();
+    } else if let Some(last) = items.last_mut() {
+        let indentation = indentation(last.first_token_mut()).map(Trivia::Whitespace);
+
+        // Whitespace at the end of the line, e.g. before a closing brace on
+        // the same line, moves behind the new last item.
+        let trailing = &mut last.last_token_mut().trailing;
+        let end = trailing.iter().rposition(|trivia| !matches!(trivia, Trivia::Whitespace(_))).map_or(0, |index| index + 1);
+        let suffix = trailing.split_off(end);
+        let separated = trailing.contains(&Trivia::Comma);
+
+        if !separated {
+            trailing.insert(0, Trivia::Comma);
+        }
+
+        item.first_token_mut().leading = indentation.map_or_else(|| layout.separator(), |indentation| vec![indentation]);
+        item.last_token_mut().trailing = separated.then_some(Trivia::Comma).into_iter().chain(suffix).collect();
+    } else {
+        match indentation(close) {
+            Some(indentation) => item.first_token_mut().leading = vec![Trivia::Whitespace(indentation + "    ")],
+            None if !layout.braces => {}
+            None if open.trailing.is_empty() => {
+                item.first_token_mut().leading = vec![whitespace(" ")];
+                item.last_token_mut().trailing = vec![whitespace(" ")];
+            }
+            None => item.last_token_mut().trailing = vec![whitespace(" ")],
+        }
+    }
+
+    items.insert(index, item);
+}
+
+// Removes an item from a comma separated list along with its trivia. When the
+// last item goes, the comma before it goes as well unless it had one itself.
+fn remove_item<T: Item>(items: &mut Vec<T>, index: usize) -> T {
+    let mut item = items.remove(index);
+
+    if index == items.len() && index > 0 {
+        let removed = mem::take(&mut item.last_token_mut().trailing);
+
+        if !removed.contains(&Trivia::Comma) {
+            let trailing = &mut items[index - 1].last_token_mut().trailing;
+
+            if let Some(comma) = trailing.iter().position(|trivia| trivia == &Trivia::Comma) {
+                trailing.remove(comma);
+
+                if comma + 1 == trailing.len() && matches!(trailing[comma], Trivia::Whitespace(_)) {
+                    trailing.remove(comma);
+                }
+            }
+
+            // Spacing before the closing delimiter is kept, a comment on the
+            // removed item's line is not.
+            if removed.iter().all(|trivia| matches!(trivia, Trivia::Whitespace(_))) {
+                trailing.extend(removed);
+            }
+        }
+    }
+
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{representation::{UnionPayload, value::Number}, deserializer::deserialize};
+    use super::*;
+
+    fn edit(aon: &str, edit: impl FnOnce(&mut Document) -> Result<()>) -> Result<String> {
+        let mut document = Document::parse(aon)?;
+        edit(&mut document)?;
+        Ok(document.to_string())
+    }
+
+    #[test]
+    fn test_set() {
+        let aon = "// deployment\n{\n    name: \"server\", // the name\n    version: \"1.2.3\",\n    a.b: { c: 1 },\n}\n";
+
+        assert_eq!(
+            edit(aon, |document| document.set("version", "1.2.4")),
+            Ok("// deployment\n{\n    name: \"server\", // the name\n    version: \"1.2.4\",\n    a.b: { c: 1 },\n}\n".to_owned()),
+        );
+        assert_eq!(
+            edit(aon, |document| document.set("a.b.c", &5)),
+            Ok("// deployment\n{\n    name: \"server\", // the name\n    version: \"1.2.3\",\n    a.b: { c: 5 },\n}\n".to_owned()),
+        );
+        assert_eq!(
+            edit(aon, |document| document.set("a.b.d.e", &true)),
+            Ok("// deployment\n{\n    name: \"server\", // the name\n    version: \"1.2.3\",\n    a.b: { c: 1, d.e: true },\n}\n".to_owned()),
+        );
+        assert_eq!(
+            edit(aon, |document| document.set("port", &8080)),
+            Ok("// deployment\n{\n    name: \"server\", // the name\n    version: \"1.2.3\",\n    a.b: { c: 1 },\n    port: 8080,\n}\n".to_owned()),
+        );
+        assert_eq!(
+            edit(aon, |document| document.set("a", &[1, 2])),
+            Ok("// deployment\n{\n    name: \"server\", // the name\n    version: \"1.2.3\",\n    a: [1, 2],\n}\n".to_owned()),
+        );
+        assert_eq!(edit("[1, /* two */ 2]", |document| document.set("1", &None::<u8>)), Ok("[1, /* two */ #none]".to_owned()));
+        assert_eq!(edit(" 1 // one", |document| document.set("", "one")), Ok(" \"one\" // one".to_owned()));
+
+        // What is not edited stays as lenient as it was written.
+        let aon = "{\n    name: 'server', // the name\n    mode: fast,\n}\n";
+        let mut document = Document::parse_with(aon, &ParseOptions::new().dialect(Dialect::Lenient)).unwrap();
+        assert_eq!(document.set("version", "1.2.4"), Ok(()));
+        assert_eq!(document.to_string(), "{\n    name: 'server', // the name\n    mode: fast,\n    version: \"1.2.4\",\n}\n");
+        assert_eq!(document.value(), deserialize(r#"{ name: "server", mode: "fast", version: "1.2.4" }"#));
+    }
+
+    #[test]
+    fn test_insert() {
+        assert_eq!(edit("{ a: 1 }", |document| document.insert("b", &2)), Ok("{ a: 1, b: 2 }".to_owned()));
+        assert_eq!(edit("{ a: 1, }", |document| document.insert("b c", &2)), Ok("{ a: 1, \"b c\": 2, }".to_owned()));
+        assert_eq!(edit("{}", |document| document.insert("a", &1)), Ok("{ a: 1 }".to_owned()));
+        assert_eq!(edit("{\n}", |document| document.insert("a", &1)), Ok("{\n    a: 1\n}".to_owned()));
+        assert_eq!(
+            edit("{\n  a: 1 // one\n}", |document| document.insert("b", &2)),
+            Ok("{\n  a: 1, // one\n  b: 2\n}".to_owned()),
+        );
+
+        assert_eq!(edit("[1, 3]", |document| document.insert("1", &2)), Ok("[1, 2, 3]".to_owned()));
+        assert_eq!(edit("[ 1 ]", |document| document.insert("0", &0)), Ok("[ 0, 1 ]".to_owned()));
+        assert_eq!(edit("[\n  1,\n]", |document| document.insert("1", &2)), Ok("[\n  1,\n  2,\n]".to_owned()));
+        assert_eq!(edit("#point(1)", |document| document.insert("1", &2)), Ok("#point(1, 2)".to_owned()));
+
+        assert_eq!(
+            edit("{ a.b: 1 }", |document| document.insert("a", &1)),
+            Err(AonError::PathExists("a".to_owned())),
+        );
+        assert_eq!(
+            edit("[1]", |document| document.insert("2", &1)),
+            Err(AonError::IndexOutOfBounds("2".to_owned(), 1)),
+        );
+        assert_eq!(AonError::IndexOutOfBounds("2".to_owned(), 1).to_string(), "index '2' is out of bounds, the length is 1");
+    }
+
+    #[test]
+    fn test_remove() {
+        assert_eq!(edit("[1, 2, 3]", |document| document.remove("1")), Ok("[1, 3]".to_owned()));
+        assert_eq!(edit("[1, 2, 3]", |document| document.remove("2")), Ok("[1, 2]".to_owned()));
+        assert_eq!(edit("[1, 2]", |document| document.remove("0")), Ok("[2]".to_owned()));
+        assert_eq!(edit("[1]", |document| document.remove("0")), Ok("[]".to_owned()));
+        assert_eq!(
+            edit("[\n  1, // one\n  // about two\n  2, // two\n  3\n]", |document| document.remove("1")),
+            Ok("[\n  1, // one\n  3\n]".to_owned()),
+        );
+        assert_eq!(
+            edit("{\n  a: 1, // one\n  b: 2 // two\n}", |document| document.remove("b")),
+            Ok("{\n  a: 1 // one\n}".to_owned()),
+        );
+        assert_eq!(edit("{ x: 0, a.b: 1, a.c: 2 }", |document| document.remove("a")), Ok("{ x: 0 }".to_owned()));
+        assert_eq!(edit("{ a: { b: 1, c: 2 } }", |document| document.remove("a.b")), Ok("{ a: { c: 2 } }".to_owned()));
+
+        assert_eq!(edit("{ a: 1 }", |document| document.remove("b")), Err(AonError::PathNotFound("b".to_owned())));
+        assert_eq!(edit("{ a: 1 }", |document| document.remove("a.b")), Err(AonError::PathNotFound("a.b".to_owned())));
+        assert_eq!(edit("{ a: 1 }", |document| document.remove("")), Err(AonError::InvalidPath(String::new())));
+    }
+
+    #[test]
+    fn test_set_variant() {
+        let aon = "{\n    mode: #fast, // default\n    shape: #circle { r: 1 },\n}";
+
+        let mut document = Document::parse(aon).unwrap();
+        document.set_variant("mode", "slow").unwrap();
+        document.set_variant("shape", "round shape").unwrap();
+
+        assert_eq!(document.to_string(), "{\n    mode: #slow, // default\n    shape: #\"round shape\" { r: 1 },\n}");
+        assert_eq!(document.value(), Ok(Value::Struct(vec![
+            ("mode".to_owned(), Value::Union("slow".to_owned(), UnionPayload::Unit)),
+            ("shape".to_owned(), Value::Union("round shape".to_owned(), UnionPayload::Struct(vec![
+                ("r".to_owned(), Value::Number(Number::PosInt(1))),
+            ]))),
+        ])));
+
+        assert_eq!(
+            document.set_variant("shape.r", "x"),
+            Err(AonError::NotAUnion("shape.r".to_owned())),
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let aon = r#"{ "a": { "b": 1 }, "mode": { "type": "fast" } }"#;
+        let options = ParseOptions::new().dialect(Dialect::Json);
+        let edit = |edit: fn(&mut Document) -> Result<()>| {
+            let mut document = Document::parse_with(aon, &options)?;
+            edit(&mut document)?;
+            document.value()?;
+            Ok::<_, AonError>(document.to_string())
+        };
+
+        assert_eq!(edit(|document| document.set("a.c", &5)), Ok(r#"{ "a": { "b": 1, "c": 5 }, "mode": { "type": "fast" } }"#.to_owned()));
+        assert_eq!(edit(|document| document.set("a", &Some(3))), Ok(r#"{ "a": { "type": "some", "values": [3] }, "mode": { "type": "fast" } }"#.to_owned()));
+        assert_eq!(edit(|document| document.set("x.y.z", &[1])), Ok(r#"{ "a": { "b": 1 }, "mode": { "type": "fast" }, "x": { "y": { "z": [1] } } }"#.to_owned()));
+        assert_eq!(edit(|document| document.set_variant("mode", "slow")), Ok(r#"{ "a": { "b": 1 }, "mode": { "type": "slow" } }"#.to_owned()));
+        assert_eq!(edit(|document| document.set_variant("a", "slow")), Err(AonError::NotAUnion("a".to_owned())));
+    }
+
+    #[test]
+    fn test_layout() {
+        let value = (vec![1, 2], Some(3));
+
+        assert_eq!(edit("{ a: 1 }", |document| document.set("b", &value)), Ok("{ a: 1, b: [[1, 2], #some(3)] }".to_owned()));
+        assert_eq!(edit("{a:1,b:2}", |document| document.insert("c", &value)), Ok("{a:1,b:2,c:[[1,2],#some(3)]}".to_owned()));
+        assert_eq!(edit("[{a: 1}]", |document| document.insert("1", &BTreeMap::from([("b", 2)]))), Ok("[{a: 1}, {b: 2}]".to_owned()));
+        assert_eq!(edit("{}", |document| document.insert("a", &1)), Ok("{ a: 1 }".to_owned()));
+        assert_eq!(edit("{\n    a:1,\n}", |document| document.insert("b", &[1, 2])), Ok("{\n    a:1,\n    b:[1, 2],\n}".to_owned()));
+    }
+}
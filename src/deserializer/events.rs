@@ -0,0 +1,406 @@
+use std::{borrow::Cow, collections::VecDeque};
+
+use crate::{representation::BorrowedValue, error::{AonError, Result}};
+
+use super::{options::{Dialect, ParseOptions}, parser::{Parser, unexpected}, tokens::{Token, TokenKind}};
+
+// What an `Events` parser reports as it reads through a document. Every
+// `Start` event is matched by an `End` once its contents have been reported,
+// unions included, so a caller only needs to track the depth to know where it
+// is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    StartStruct,
+    // The key of the value that follows.
+    Key(Cow<'a, str>),
+    // Followed by the payload, a struct or tuple, if there is one.
+    StartUnion(Cow<'a, str>),
+    StartTuple,
+    StartArray,
+    // A null, bool, number or string.
+    Scalar(BorrowedValue<'a>),
+    End,
+}
+
+// The containers the parser is inside of, innermost last.
+enum Frame {
+    // `items` counts the fields looked for so far, every one past the first
+    // comes after a separator.
+    Struct { items: usize },
+    // An array or tuple, ended by `close`.
+    Sequence { close: TokenKind<'static>, items: usize },
+    // `payload` is set once the payload, if any, has been started.
+    Union { payload: bool },
+    // The struct implied by a segment of a dotted key, which holds that one
+    // field only.
+    Dotted,
+}
+
+// A pull parser that reports a document as a sequence of events instead of
+// building a value tree, so only the containers it is in are kept in memory.
+//
+// Dotted keys are reported as the nested structs they stand for, but are not
+// merged, so `a.b: 1, a.c: 2` reports the key `a` twice. Key collisions that
+// merging would find go unreported for the same reason, whatever the
+// `DuplicateKeys` setting. In the JSON dialect objects are reported as they
+// are written, `type` included, as telling a union apart takes all of it.
+pub struct Events<'a, I> {
+    parser: Parser<'a, I>,
+    stack: Vec<Frame>,
+    // Events that have been parsed but not returned yet.
+    queue: VecDeque<Event<'a>>,
+    value_next: bool,
+    started: bool,
+    done: bool,
+}
+
+impl<'a, I: Iterator<Item = Result<Token<'a>>>> Events<'a, I> {
+    pub fn new(tokens: I) -> Events<'a, I> {
+        Events {
+            parser: Parser::new(tokens),
+            stack: vec![],
+            queue: VecDeque::new(),
+            value_next: false,
+            started: false,
+            done: false,
+        }
+    }
+
+    pub fn with_options(mut self, options: &ParseOptions) -> Events<'a, I> {
+        self.parser = self.parser.with_options(options);
+        self
+    }
+
+    // How many containers the parser is inside of.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // Reads past the value that comes next, such as the one after a `Key`
+    // event, without reporting it. Where a field comes next, its key is read
+    // past along with its value. Returns false where no value comes next, as
+    // at the end of an array.
+    pub fn skip_value(&mut self) -> Result<bool> {
+        let mut depth = 0usize;
+
+        while let Some(event) = self.next().transpose()? {
+            match event {
+                Event::StartStruct | Event::StartUnion(_) | Event::StartTuple | Event::StartArray => depth += 1,
+                Event::End if depth == 0 => {
+                    self.queue.push_front(Event::End);
+                    return Ok(false);
+                }
+                Event::End => depth -= 1,
+                Event::Key(_) if depth == 0 => continue,
+                Event::Key(_) | Event::Scalar(_) => {}
+            }
+
+            if depth == 0 {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn step(&mut self) -> Result<Option<Event<'a>>> {
+        if self.value_next {
+            self.value_next = false;
+            return self.start_value().map(Some);
+        }
+
+        let Some(frame) = self.stack.last_mut() else {
+            if self.started {
+                return match self.parser.bump()? {
+                    Token { kind: TokenKind::EOF, .. } => Ok(None),
+                    other => Err(unexpected(&other)),
+                };
+            }
+
+            self.started = true;
+            return self.start_value().map(Some);
+        };
+
+        match frame {
+            Frame::Struct { items } => {
+                *items += 1;
+                let items = *items;
+                self.next_field(items).map(Some)
+            }
+            Frame::Sequence { close, items } => {
+                *items += 1;
+                let (close, items) = (close.clone(), *items);
+                self.next_element(&close, items).map(Some)
+            }
+            Frame::Union { payload: true } | Frame::Dotted => Ok(Some(self.end())),
+            Frame::Union { payload } => {
+                *payload = true;
+
+                match self.parser.first()?.kind {
+                    TokenKind::LeftBrace => self.start_value().map(Some),
+                    TokenKind::LeftParen => {
+                        self.parser.bump()?;
+                        self.stack.push(Frame::Sequence { close: TokenKind::RightParen, items: 0 });
+                        Ok(Some(Event::StartTuple))
+                    }
+                    _ => Ok(Some(self.end())),
+                }
+            }
+        }
+    }
+
+    // The key of the next field of a struct, or the end of it.
+    fn next_field(&mut self, items: usize) -> Result<Event<'a>> {
+        if items > 1 && !self.parser.eat(&TokenKind::Comma)? {
+            self.parser.expect(&TokenKind::RightBrace)?;
+            return Ok(self.end());
+        }
+
+        self.reject_trailing_comma(&TokenKind::RightBrace, items)?;
+
+        if self.parser.eat(&TokenKind::RightBrace)? {
+            return Ok(self.end());
+        }
+
+        self.check_items(items)?;
+        let (path, _) = self.parser.parse_key_path()?;
+        self.parser.expect(&TokenKind::Colon)?;
+        self.value_next = true;
+
+        let mut path = path.into_iter();
+        let key = path.next().expect("key paths have a segment");
+
+        for segment in path {
+            self.stack.push(Frame::Dotted);
+            self.queue.extend([Event::StartStruct, Event::Key(segment)]);
+        }
+
+        Ok(Event::Key(key))
+    }
+
+    // The start of the next element of an array or tuple, or the end of it.
+    fn next_element(&mut self, close: &TokenKind, items: usize) -> Result<Event<'a>> {
+        if items > 1 && !self.parser.eat(&TokenKind::Comma)? {
+            self.parser.expect(close)?;
+            return Ok(self.end());
+        }
+
+        self.reject_trailing_comma(close, items)?;
+
+        if self.parser.eat(close)? {
+            return Ok(self.end());
+        }
+
+        self.check_items(items)?;
+        self.start_value()
+    }
+
+    // JSON has no trailing commas, past the first item one has just been
+    // read.
+    fn reject_trailing_comma(&mut self, close: &TokenKind, items: usize) -> Result<()> {
+        match items > 1 && self.parser.options().dialect == Dialect::Json && &self.parser.first()?.kind == close {
+            true => Err(unexpected(self.parser.first()?)),
+            false => Ok(()),
+        }
+    }
+
+    fn check_items(&mut self, items: usize) -> Result<()> {
+        match self.parser.options().max_elements {
+            Some(max_elements) if items > max_elements => Err(AonError::ElementLimitExceeded(max_elements, self.parser.first()?.span)),
+            _ => Ok(()),
+        }
+    }
+
+    // Counted like the parser counts them, a union and its payload being one
+    // level.
+    fn check_depth(&mut self) -> Result<()> {
+        let levels = self.stack.iter()
+            .filter(|frame| matches!(frame, Frame::Struct { .. } | Frame::Sequence { .. } | Frame::Union { payload: false }))
+            .count();
+
+        match self.parser.options().max_depth {
+            Some(max_depth) if levels >= max_depth => Err(AonError::DepthLimitExceeded(max_depth, self.parser.first()?.span)),
+            _ => Ok(()),
+        }
+    }
+
+    fn start_value(&mut self) -> Result<Event<'a>> {
+        match self.parser.first()?.kind {
+            TokenKind::LeftBrace => {
+                self.check_depth()?;
+                self.parser.bump()?;
+                self.stack.push(Frame::Struct { items: 0 });
+                Ok(Event::StartStruct)
+            }
+            TokenKind::LeftBracket => {
+                self.check_depth()?;
+                self.parser.bump()?;
+                self.stack.push(Frame::Sequence { close: TokenKind::RightBracket, items: 0 });
+                Ok(Event::StartArray)
+            }
+            TokenKind::Hash => {
+                self.check_depth()?;
+                self.parser.bump()?;
+                let (variant, _) = self.parser.parse_name()?;
+                self.parser.reject_comment()?;
+                self.stack.push(Frame::Union { payload: false });
+                Ok(Event::StartUnion(variant))
+            }
+            _ => self.parser.parse_primitive().map(Event::Scalar),
+        }
+    }
+
+    fn end(&mut self) -> Event<'a> {
+        self.stack.pop();
+        Event::End
+    }
+}
+
+// Returns the events up to the end of the document, or up to and including
+// the first error.
+impl<'a, I: Iterator<Item = Result<Token<'a>>>> Iterator for Events<'a, I> {
+    type Item = Result<Event<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.queue.pop_front() {
+            return Some(Ok(event));
+        }
+
+        if self.done {
+            return None;
+        }
+
+        match self.step() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{representation::value::Number, error::AonError, deserializer::{parse_events, parse_events_with, lexer::Lexer}};
+    use super::*;
+
+    fn events(aon: &str) -> Result<Vec<Event<'_>>> {
+        parse_events(aon).collect()
+    }
+
+    fn key(key: &str) -> Event<'_> {
+        Event::Key(key.into())
+    }
+
+    fn number(number: u64) -> Event<'static> {
+        Event::Scalar(BorrowedValue::Number(Number::PosInt(number)))
+    }
+
+    #[test]
+    fn test_events() {
+        let aon = r#"{ "name": "a", /* skipped */ list: [1, null,], shape: #circle { r: 2 }, none: #none, pair: #pair(1, true) }"#;
+
+        assert_eq!(events(aon), Ok(vec![
+            Event::StartStruct,
+            key("name"), Event::Scalar(BorrowedValue::String("a".into())),
+            key("list"), Event::StartArray, number(1), Event::Scalar(BorrowedValue::Null), Event::End,
+            key("shape"), Event::StartUnion("circle".into()), Event::StartStruct, key("r"), number(2), Event::End, Event::End,
+            key("none"), Event::StartUnion("none".into()), Event::End,
+            key("pair"), Event::StartUnion("pair".into()), Event::StartTuple, number(1), Event::Scalar(BorrowedValue::Bool(true)), Event::End, Event::End,
+            Event::End,
+        ]));
+
+        assert_eq!(events("42"), Ok(vec![number(42)]));
+        assert_eq!(events("{}"), Ok(vec![Event::StartStruct, Event::End]));
+        assert_eq!(events("[[], #a()]"), Ok(vec![
+            Event::StartArray, Event::StartArray, Event::End, Event::StartUnion("a".into()), Event::StartTuple, Event::End, Event::End, Event::End,
+        ]));
+    }
+
+    #[test]
+    fn test_dotted_keys() {
+        assert_eq!(events("{ a.b.c: 1, a.d: 2 }"), Ok(vec![
+            Event::StartStruct,
+            key("a"), Event::StartStruct, key("b"), Event::StartStruct, key("c"), number(1), Event::End, Event::End,
+            key("a"), Event::StartStruct, key("d"), number(2), Event::End,
+            Event::End,
+        ]));
+
+        assert_eq!(events("{ a.b: [1], c: 2 }").unwrap().len(), 11);
+        assert_eq!(events("{ a.\"b.c\": 1 }"), Ok(vec![
+            Event::StartStruct, key("a"), Event::StartStruct, key("b.c"), number(1), Event::End, Event::End,
+        ]));
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let mut events = parse_events("{ big: { a: [1, 2, { b: #c(3) }] }, small: 4 }");
+
+        assert_eq!(events.next(), Some(Ok(Event::StartStruct)));
+        assert_eq!(events.next(), Some(Ok(key("big"))));
+        assert_eq!(events.skip_value(), Ok(true));
+        assert_eq!(events.depth(), 1);
+        assert_eq!(events.next(), Some(Ok(key("small"))));
+        assert_eq!(events.skip_value(), Ok(true));
+        assert_eq!(events.skip_value(), Ok(false));
+        assert_eq!(events.next(), Some(Ok(Event::End)));
+        assert_eq!(events.next(), None);
+
+        // Skipping whole fields, dotted keys included, before reading one.
+        let mut events = parse_events("{ a: 1, b.c: [2], d: 3 }");
+        assert_eq!(events.next(), Some(Ok(Event::StartStruct)));
+        assert_eq!(events.skip_value(), Ok(true));
+        assert_eq!(events.skip_value(), Ok(true));
+        assert_eq!(events.next(), Some(Ok(key("d"))));
+        assert_eq!(events.next(), Some(Ok(number(3))));
+        assert_eq!(events.skip_value(), Ok(false));
+        assert_eq!(events.next(), Some(Ok(Event::End)));
+
+        // Counting the elements of an array without looking at them.
+        let mut events = Events::new(Lexer::from_reader(&b"[{ a: 1 }, [2, 3], #b, 4]"[..]));
+        let mut count = 0;
+        assert_eq!(events.next(), Some(Ok(Event::StartArray)));
+
+        while events.skip_value().unwrap() {
+            count += 1;
+        }
+
+        assert_eq!(count, 4);
+        assert_eq!(events.next(), Some(Ok(Event::End)));
+        assert_eq!(events.next(), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut stream = parse_events("[1, 2 3]");
+        assert_eq!(stream.by_ref().take(3).collect::<Result<Vec<_>>>(), Ok(vec![Event::StartArray, number(1), number(2)]));
+        assert!(matches!(stream.next(), Some(Err(AonError::UnexpectedToken(TokenKind::Number(Number::PosInt(3)), _)))));
+        assert_eq!(stream.next(), None);
+
+        assert!(matches!(events("{ a: 1 } 2"), Err(AonError::UnexpectedToken(..))));
+        assert!(matches!(events("{ a: 1"), Err(AonError::UnexpectedEndOfFile(_))));
+        assert!(matches!(events("[nul]"), Err(AonError::InvalidKeyword(..))));
+    }
+
+    #[test]
+    fn test_dialects() {
+        let with = |aon, dialect| parse_events_with(aon, &ParseOptions::new().dialect(dialect)).collect::<Result<Vec<_>>>();
+        let string = |string: &'static str| Event::Scalar(BorrowedValue::String(string.into()));
+
+        assert_eq!(with("['a', b]", Dialect::Lenient), Ok(vec![Event::StartArray, string("a"), string("b"), Event::End]));
+        assert_eq!(with("#/* x */some", Dialect::Lenient), Ok(vec![Event::StartUnion("some".into()), Event::End]));
+        assert!(matches!(with("#/* x */some", Dialect::Strict), Err(AonError::UnexpectedToken(TokenKind::BlockComment(_), _))));
+        assert!(matches!(with("{ a./* x */b: 1 }", Dialect::Strict), Err(AonError::UnexpectedToken(TokenKind::BlockComment(_), _))));
+
+        let json = r#"{ "type": "a.b", "values": [1] }"#;
+        assert_eq!(with(json, Dialect::Json).map(|events| events.len()), Ok(8));
+        assert!(matches!(with("[1, ]", Dialect::Json), Err(AonError::UnexpectedToken(TokenKind::RightBracket, _))));
+        assert!(matches!(with(r#"{ "a": 1, }"#, Dialect::Json), Err(AonError::UnexpectedToken(TokenKind::RightBrace, _))));
+        assert_eq!(with("[1, ]", Dialect::Strict).map(|events| events.len()), Ok(3));
+    }
+}
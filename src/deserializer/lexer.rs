@@ -1,155 +1,615 @@
-use std::vec;
+use std::{borrow::Cow, io::Read, iter::Peekable, str::Chars};
 
 use crate::{
     representation::value::Number,
     error::{AonError, Result}
 };
 
-use super::{cursor, tokens::{Token, self}};
+use super::{cursor, options::{Dialect, ParseOptions}, span::{Position, Span}, tokens::{self, Token, TokenKind}};
 
-pub fn tokenize(aon: &str) -> Result<Vec<Token>> {
-    let mut tokens = vec![];
-    let mut cursor = cursor::Cursor::new(aon);
+pub fn tokenize(aon: &str) -> Result<Vec<Token<'_>>> {
+    Lexer::new(aon).collect()
+}
+
+// Lexes tokens one at a time as they are asked for, so the parser never
+// needs the whole token stream in memory. The last token is always EOF,
+// after it or after an error the lexer is exhausted.
+pub struct Lexer<'a> {
+    cursor: cursor::Cursor<'a>,
+    done: bool,
+    recovering: bool,
+    options: ParseOptions,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(aon: &'a str) -> Lexer<'a> {
+        Lexer::starting_at(aon, Position::START)
+    }
+
+    // Lexes `aon` taken from a larger text, with spans relative to that.
+    pub(crate) fn starting_at(aon: &'a str, position: Position) -> Lexer<'a> {
+        Lexer {
+            cursor: cursor::Cursor::starting_at(aon, position),
+            done: false,
+            recovering: false,
+            options: ParseOptions::default(),
+        }
+    }
+
+    // Lexes text as it is read from `reader`. Tokens are always owned.
+    pub fn from_reader(reader: impl Read + 'a) -> Lexer<'a> {
+        Lexer {
+            cursor: cursor::Cursor::from_reader(reader),
+            done: false,
+            recovering: false,
+            options: ParseOptions::default(),
+        }
+    }
 
-    while !cursor.is_eof() {
-        let token = tokenize_next(&mut cursor)?;
-        tokens.push(token);
+    // Enforces the limits in `options` that apply to text, the size of the
+    // input and the length of strings, and lexes the dialect it sets.
+    pub fn with_options(mut self, options: &ParseOptions) -> Lexer<'a> {
+        if let Some(max_size) = options.max_size {
+            self.cursor.limit(max_size);
+        }
+
+        self.options = options.clone();
+        self
+    }
+
+    // Carries on lexing after an error instead of ending there, skipping at
+    // least the char it was found at. I/O errors still end the input.
+    pub fn recovering(mut self) -> Lexer<'a> {
+        self.recovering = true;
+        self
     }
+}
+
+impl<'a> Lexer<'a> {
+    fn check_length(&self, token: Token<'a>) -> Result<Token<'a>> {
+        match (&token.kind, self.options.max_string_length) {
+            (TokenKind::Word(text) | TokenKind::Ident(text), Some(max)) if text.len() > max =>
+                Err(AonError::StringLimitExceeded(max, token.span)),
+            _ => Ok(token),
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>>;
+
+    fn next(&mut self) -> Option<Result<Token<'a>>> {
+        if self.done {
+            return None;
+        }
+
+        self.cursor.eat_while(char::is_whitespace);
+        let start = self.cursor.position();
+
+        let token = match self.cursor.is_eof() {
+            true => Ok(Token::new(TokenKind::EOF, Span::new(start, start))),
+            false => tokenize_next(&mut self.cursor, self.options.dialect).map(|kind| Token::new(kind, Span::new(start, self.cursor.position()))),
+        };
+
+        // A failed read ends the input early, which is what the token or
+        // error lexed last would be based on.
+        let token = match self.cursor.take_error() {
+            Some(error) => Err(error),
+            None => token.and_then(|token| self.check_length(token)),
+        };
+
+        self.done = match &token {
+            Err(AonError::Io(..) | AonError::SizeLimitExceeded(..)) | Ok(Token { kind: TokenKind::EOF, .. }) => true,
+            Err(_) if self.recovering => {
+                if self.cursor.position() == start {
+                    self.cursor.bump();
+                }
 
-    tokens.push(Token::EOF);
-    Ok(tokens)
+                false
+            }
+            Err(_) => true,
+            Ok(_) => false,
+        };
+
+        Some(token)
+    }
 }
 
-fn tokenize_next(cursor: &mut cursor::Cursor) -> Result<Token> {
-    return match cursor.first() {
+fn tokenize_next<'a>(cursor: &mut cursor::Cursor<'a>, dialect: Dialect) -> Result<TokenKind<'a>> {
+    match cursor.first() {
+        // JSON has no comments, unions or dot notation.
+        tokens::SLASH | tokens::HASH | tokens::DOT | tokens::LEFT_PAREN | tokens::RIGHT_PAREN if dialect == Dialect::Json =>
+            Err(cursor.unexpected_character()),
+
         tokens::SLASH => {
             cursor.bump();
 
             match cursor.first() {
                 tokens::SLASH => {
                     cursor.bump();
-                    let mut comment = String::new();
-                    cursor.eat_while(|c| {
-                        if c == '\n' {
-                            false
-                        } else {
-                            comment.push(c);
-                            true
-                        }
-                    });
-
-                    Ok(Token::Comment(comment))
+                    let mark = cursor.mark();
+                    cursor.eat_while(|c| c != '\n');
+
+                    Ok(TokenKind::Comment(cursor.since(mark)))
+                }
+                tokens::STAR => {
+                    cursor.bump();
+                    tokenize_block_comment(cursor)
                 }
-                _ => return Err(AonError::UnexpectedCharacter(cursor.first())),
+                _ => Err(cursor.unexpected_character()),
             }
         }
 
-        tokens::SINGLE_QUOTE => {
-            cursor.bump();
-            let mut string = String::new();
-            cursor.eat_while(|c| {
-                if c == tokens::SINGLE_QUOTE {
-                    false
-                } else {
-                    string.push(c);
-                    true
-                }
-            });
+        tokens::DOUBLE_QUOTE => tokenize_string(cursor, tokens::DOUBLE_QUOTE),
 
-            cursor.bump();
-
-            Ok(Token::Word(string))
-        }
-
-        tokens::DOUBLE_QUOTE => {
-            cursor.bump();
-            let mut string = String::new();
-            cursor.eat_while(|c| {
-                if c == tokens::DOUBLE_QUOTE {
-                    false
-                } else {
-                    string.push(c);
-                    true
-                }
-            });
+        tokens::SINGLE_QUOTE if dialect == Dialect::Lenient => tokenize_string(cursor, tokens::SINGLE_QUOTE),
 
+        tokens::COLON => {
             cursor.bump();
-
-            Ok(Token::Word(string))
+            Ok(TokenKind::Colon)
         }
 
-        tokens::COLON => {
+        tokens::COMMA => {
             cursor.bump();
-            Ok(Token::Colon)
+            Ok(TokenKind::Comma)
         }
 
-        tokens::COMMA => {
+        // A number missing its integer part, like `.5`, is reported as such
+        // rather than as a stray dot.
+        tokens::DOT if cursor.second().is_ascii_digit() => tokenize_number(cursor, dialect),
+
+        tokens::DOT => {
             cursor.bump();
-            Ok(Token::Comma)
+            Ok(TokenKind::Dot)
         }
 
         tokens::HASH => {
             cursor.bump();
-            Ok(Token::Hash)
+            Ok(TokenKind::Hash)
         }
 
         tokens::LEFT_BRACE => {
             cursor.bump();
-            Ok(Token::LeftBrace)
+            Ok(TokenKind::LeftBrace)
         }
 
         tokens::RIGHT_BRACE => {
             cursor.bump();
-            Ok(Token::RightBrace)
+            Ok(TokenKind::RightBrace)
         }
 
         tokens::LEFT_BRACKET => {
             cursor.bump();
-            Ok(Token::LeftBracket)
+            Ok(TokenKind::LeftBracket)
         }
 
         tokens::RIGHT_BRACKET => {
             cursor.bump();
-            Ok(Token::RightBracket)
+            Ok(TokenKind::RightBracket)
         }
-        
-        other if other.is_whitespace() => {
+
+        tokens::LEFT_PAREN => {
             cursor.bump();
-            return tokenize_next(cursor);
+            Ok(TokenKind::LeftParen)
         }
 
-        other if other.is_numeric() => {
-            let mut number = String::new();
-            cursor.eat_while(|c| {
-                if c.is_numeric() || c == '.' {
-                    number.push(c);
-                    true
-                } else {
-                    false
-                }
-            });
+        tokens::RIGHT_PAREN => {
+            cursor.bump();
+            Ok(TokenKind::RightParen)
+        }
+
+        c if c.is_ascii_digit() || c == tokens::MINUS => tokenize_number(cursor, dialect),
+
+        c if tokens::is_word_start(c) => tokenize_word(cursor),
 
-            match Number::new(&number) {
-                Some(n) => Ok(Token::Number(n)),
-                None => Err(AonError::InvalidNumber(number)),
+        _ => Err(cursor.unexpected_character()),
+    }
+}
+
+// Block comments run until the first `*/` and do not nest. One left open
+// swallows the rest of the input, so it is reported at the end of it.
+fn tokenize_block_comment<'a>(cursor: &mut cursor::Cursor<'a>) -> Result<TokenKind<'a>> {
+    let mark = cursor.mark();
+
+    loop {
+        match cursor.first() {
+            tokens::STAR if cursor.second() == tokens::SLASH => {
+                let comment = cursor.since(mark);
+                cursor.bump();
+                cursor.bump();
+                return Ok(TokenKind::BlockComment(comment));
+            }
+            _ if cursor.is_eof() => return Err(cursor.unexpected_character()),
+            _ => {
+                cursor.bump();
             }
         }
+    }
+}
+
+// Reads a whole word before matching it against the keywords, so that `nul`
+// or `nullable` become identifiers instead of being partially read as `null`.
+fn tokenize_word<'a>(cursor: &mut cursor::Cursor<'a>) -> Result<TokenKind<'a>> {
+    let mark = cursor.mark();
+    cursor.eat_while(tokens::is_word_continue);
+    let word = cursor.since(mark);
+
+    match word.as_ref() {
+        tokens::NULL => Ok(TokenKind::Null),
+        tokens::TRUE => Ok(TokenKind::Bool(true)),
+        tokens::FALSE => Ok(TokenKind::Bool(false)),
+        _ => Ok(TokenKind::Ident(word)),
+    }
+}
 
-        other if other == 'n' => {
-            cursor.eat_text(tokens::NULL)?;
-            Ok(Token::Null)
+// Reads the longest run of characters that can make up a number, so that
+// malformed numbers like `1..2` are reported whole, then checks it against
+// the grammar `-? digits (. digits)? ([eE] [+-]? digits)?`. JSON also has no
+// leading zeros, as in `01`.
+fn tokenize_number<'a>(cursor: &mut cursor::Cursor<'a>, dialect: Dialect) -> Result<TokenKind<'a>> {
+    let start = cursor.position();
+    let mark = cursor.mark();
+
+    cursor.eat_while(|c| c.is_ascii_digit() || matches!(c, tokens::DOT | tokens::MINUS | tokens::PLUS | 'e' | 'E'));
+    let number = cursor.since(mark);
+
+    let digits = number.strip_prefix(tokens::MINUS).unwrap_or(&number);
+    let leading_zero = dialect == Dialect::Json && digits.starts_with('0') && digits[1..].starts_with(|c: char| c.is_ascii_digit());
+
+    match (is_valid_number(&number) && !leading_zero).then(|| Number::new(&number)).flatten() {
+        Some(n) => Ok(TokenKind::Number(n)),
+        None => Err(AonError::InvalidNumber(number.into_owned(), Span::new(start, cursor.position()))),
+    }
+}
+
+fn is_valid_number(number: &str) -> bool {
+    fn eat_digits(chars: &mut Peekable<Chars>) -> bool {
+        let mut any = false;
+
+        while chars.next_if(char::is_ascii_digit).is_some() {
+            any = true;
         }
 
-        other if other == 't' => {
-            cursor.eat_text(tokens::TRUE)?;
-            Ok(Token::Bool(true))
+        any
+    }
+
+    let mut chars = number.chars().peekable();
+
+    chars.next_if_eq(&tokens::MINUS);
+
+    if !eat_digits(&mut chars) {
+        return false;
+    }
+
+    if chars.next_if_eq(&tokens::DOT).is_some() && !eat_digits(&mut chars) {
+        return false;
+    }
+
+    if chars.next_if(|c| matches!(c, 'e' | 'E')).is_some() {
+        chars.next_if(|c| matches!(*c, tokens::PLUS | tokens::MINUS));
+
+        if !eat_digits(&mut chars) {
+            return false;
         }
+    }
+
+    chars.next().is_none()
+}
 
-        other if other == 'f' => {
-            cursor.eat_text(tokens::FALSE)?;
-            Ok(Token::Bool(false))
+// Strings without escapes are borrowed from the input, the first escape
+// copies what was read so far into an owned string that the rest is added to.
+fn tokenize_string<'a>(cursor: &mut cursor::Cursor<'a>, quote: char) -> Result<TokenKind<'a>> {
+    cursor.bump();
+    let mark = cursor.mark();
+
+    while cursor.first() != quote && cursor.first() != tokens::BACKSLASH && !cursor.is_eof() {
+        cursor.bump();
+    }
+
+    let read = cursor.since(mark);
+
+    if cursor.first() == quote {
+        cursor.bump();
+        return Ok(TokenKind::Word(read));
+    }
+
+    let mut string = read.into_owned();
+
+    loop {
+        let start = cursor.position();
+
+        match cursor.bump() {
+            Some(c) if c == quote => return Ok(TokenKind::Word(Cow::Owned(string))),
+            Some(tokens::BACKSLASH) => match tokenize_escape(cursor, start) {
+                Ok(c) => string.push(c),
+                // The rest of the string is read regardless, so that lexing
+                // can carry on after it.
+                Err(error) => {
+                    skip_string(cursor, quote);
+                    return Err(error);
+                }
+            },
+            Some(c) => string.push(c),
+            None => return Err(cursor.unexpected_character()),
+        }
+    }
+}
+
+// Reads up to and including the closing quote of a string, if there is one.
+fn skip_string(cursor: &mut cursor::Cursor, quote: char) {
+    while let Some(c) = cursor.bump() {
+        match c {
+            c if c == quote => return,
+            tokens::BACKSLASH => {
+                cursor.bump();
+            }
+            _ => {}
         }
+    }
+}
 
-        other => Err(AonError::UnexpectedCharacter(other)),
+// Reads the escape sequence following a backslash at `start`.
+fn tokenize_escape(cursor: &mut cursor::Cursor, start: Position) -> Result<char> {
+    let mut escape = String::from(tokens::BACKSLASH);
+
+    let c = match cursor.bump() {
+        Some(c) => c,
+        None => return Err(cursor.unexpected_character()),
     };
+
+    escape.push(c);
+
+    let unescaped = match c {
+        '"' | '\'' | '\\' | '/' => Some(c),
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        'b' => Some('\u{8}'),
+        'f' => Some('\u{c}'),
+        '0' => Some('\0'),
+        'u' => tokenize_unicode_escape(cursor, &mut escape),
+        _ => None,
+    };
+
+    unescaped.ok_or_else(|| AonError::InvalidEscape(escape, Span::new(start, cursor.position())))
+}
+
+// Reads the code point of a `\u{XXXXXX}` or `\uXXXX` escape. The latter
+// encodes UTF-16, so characters outside the basic multilingual plane are
+// written as a surrogate pair, `\uD83D\uDE00`.
+fn tokenize_unicode_escape(cursor: &mut cursor::Cursor, escape: &mut String) -> Option<char> {
+    if cursor.first() == tokens::LEFT_BRACE {
+        escape.push(tokens::LEFT_BRACE);
+        cursor.bump();
+
+        let mut digits = 0;
+
+        while cursor.first().is_ascii_hexdigit() && digits < 6 {
+            escape.extend(cursor.bump());
+            digits += 1;
+        }
+
+        if cursor.first() != tokens::RIGHT_BRACE || digits == 0 {
+            return None;
+        }
+
+        escape.push(tokens::RIGHT_BRACE);
+        cursor.bump();
+
+        return char::from_u32(u32::from_str_radix(&escape[3..escape.len() - 1], 16).ok()?);
+    }
+
+    let high = eat_hex(cursor, escape)?;
+
+    match high {
+        0xD800..=0xDBFF => {
+            for expected in [tokens::BACKSLASH, 'u'] {
+                if cursor.first() != expected {
+                    return None;
+                }
+
+                escape.extend(cursor.bump());
+            }
+
+            let low = eat_hex(cursor, escape)?;
+
+            match low {
+                0xDC00..=0xDFFF => char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)),
+                _ => None,
+            }
+        }
+        _ => char::from_u32(high),
+    }
+}
+
+fn eat_hex(cursor: &mut cursor::Cursor, escape: &mut String) -> Option<u32> {
+    let mut value = 0;
+
+    for _ in 0..4 {
+        let digit = cursor.first().to_digit(16)?;
+        escape.extend(cursor.bump());
+        value = value * 16 + digit;
+    }
+
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deserializer::span::{position, position_at, span};
+    use super::*;
+
+    fn word(aon: &str) -> Result<TokenKind<'_>> {
+        tokenize(aon).map(|tokens| tokens[0].kind.clone())
+    }
+
+    fn number(aon: &str) -> Result<Number> {
+        match word(aon)? {
+            TokenKind::Number(number) => Ok(number),
+            other => panic!("expected a number, found {}", other),
+        }
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(number("0"), Ok(Number::PosInt(0)));
+        assert_eq!(number("-0"), Ok(Number::PosInt(0)));
+        assert_eq!(number("42"), Ok(Number::PosInt(42)));
+        assert_eq!(number("18446744073709551616"), Ok(Number::BigPosInt(18446744073709551616)));
+        assert_eq!(number("-42"), Ok(Number::NegInt(-42)));
+        assert_eq!(number("-9223372036854775809"), Ok(Number::BigNegInt(-9223372036854775809)));
+        assert_eq!(number("-3.2"), Ok(Number::Float(-3.2)));
+        assert_eq!(number("1e9"), Ok(Number::Float(1e9)));
+        assert_eq!(number("2.5E-3"), Ok(Number::Float(2.5e-3)));
+        assert_eq!(number("-1.5e+2"), Ok(Number::Float(-150.0)));
+    }
+
+    #[test]
+    fn test_invalid_numbers() {
+        let invalid = |number: &str| Err(AonError::InvalidNumber(number.to_owned(), span(0, number.len())));
+
+        for malformed in ["1..2", ".5", "1.", "-", "--1", "1e", "1e+", "1.2.3", "1e5e5", "1-2", "1e999"] {
+            assert_eq!(number(malformed), invalid(malformed));
+        }
+
+        assert_eq!(
+            tokenize("[1,-2]").map(|tokens| tokens.into_iter().map(|token| token.kind).collect::<Vec<_>>()),
+            Ok(vec![
+                TokenKind::LeftBracket,
+                TokenKind::Number(Number::PosInt(1)),
+                TokenKind::Comma,
+                TokenKind::Number(Number::NegInt(-2)),
+                TokenKind::RightBracket,
+                TokenKind::EOF,
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_keywords() {
+        assert_eq!(word("null"), Ok(TokenKind::Null));
+        assert_eq!(word("true"), Ok(TokenKind::Bool(true)));
+        assert_eq!(word("false"), Ok(TokenKind::Bool(false)));
+
+        for ident in ["nul", "nope", "nullable", "True", "false_", "t", "_private", "kebab-case", "snake_case2", "größe"] {
+            assert_eq!(word(ident), Ok(TokenKind::Ident(ident.into())));
+        }
+
+        assert_eq!(
+            tokenize("{a.b-c:null,#some}").map(|tokens| tokens.into_iter().map(|token| token.kind).collect::<Vec<_>>()),
+            Ok(vec![
+                TokenKind::LeftBrace,
+                TokenKind::Ident("a".into()),
+                TokenKind::Dot,
+                TokenKind::Ident("b-c".into()),
+                TokenKind::Colon,
+                TokenKind::Null,
+                TokenKind::Comma,
+                TokenKind::Hash,
+                TokenKind::Ident("some".into()),
+                TokenKind::RightBrace,
+                TokenKind::EOF,
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_lexer() {
+        let mut lexer = Lexer::new("[1, $]");
+
+        assert_eq!(lexer.next().map(|token| token.map(|token| token.kind)), Some(Ok(TokenKind::LeftBracket)));
+        assert_eq!(lexer.next().map(|token| token.map(|token| token.kind)), Some(Ok(TokenKind::Number(Number::PosInt(1)))));
+        assert_eq!(lexer.next().map(|token| token.map(|token| token.kind)), Some(Ok(TokenKind::Comma)));
+
+        assert_eq!(lexer.next(), Some(Err(AonError::UnexpectedCharacter('$', Span::char(position(4), '$')))));
+        assert_eq!(lexer.next(), None);
+
+        let end = position_at(3, 2, 2);
+        assert_eq!(Lexer::new(" \n ").collect::<Vec<_>>(), vec![Ok(Token::new(TokenKind::EOF, Span::new(end, end)))]);
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(word("// line"), Ok(TokenKind::Comment(" line".into())));
+        assert_eq!(word("/* block */"), Ok(TokenKind::BlockComment(" block ".into())));
+        assert_eq!(word("/* multi\n * line **/"), Ok(TokenKind::BlockComment(" multi\n * line *".into())));
+        assert_eq!(word("/* /* */"), Ok(TokenKind::BlockComment(" /* ".into())));
+
+        assert_eq!(
+            tokenize("[1/**/,2]").map(|tokens| tokens.into_iter().map(|token| token.kind).collect::<Vec<_>>()),
+            Ok(vec![
+                TokenKind::LeftBracket,
+                TokenKind::Number(Number::PosInt(1)),
+                TokenKind::BlockComment("".into()),
+                TokenKind::Comma,
+                TokenKind::Number(Number::PosInt(2)),
+                TokenKind::RightBracket,
+                TokenKind::EOF,
+            ]),
+        );
+
+        let end = position_at(10, 2, 3);
+        assert_eq!(word("/* open\n *"), Err(AonError::UnexpectedEndOfFile(Span::new(end, end))));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(word(r#""a\"b\\c\/d""#), Ok(TokenKind::Word("a\"b\\c/d".into())));
+        assert_eq!(word(r#""\n\t\r\b\f\0""#), Ok(TokenKind::Word("\n\t\r\u{8}\u{c}\0".into())));
+        assert_eq!(word(r#""\u{e9}\u{1F600}é😀""#), Ok(TokenKind::Word("é😀é😀".into())));
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        assert_eq!(word(r#""a\qb""#), Err(AonError::InvalidEscape(r"\q".to_owned(), span(2, 4))));
+        assert_eq!(word(r#""\u{}""#), Err(AonError::InvalidEscape(r"\u{".to_owned(), span(1, 4))));
+        assert_eq!(word(r#""\u{110000}""#), Err(AonError::InvalidEscape(r"\u{110000}".to_owned(), span(1, 11))));
+        assert_eq!(word(r#""\u12""#), Err(AonError::InvalidEscape(r"\u12".to_owned(), span(1, 5))));
+        assert_eq!(word(r#""\uD83D""#), Err(AonError::InvalidEscape(r"\uD83D".to_owned(), span(1, 7))));
+        assert_eq!(word(r#""\uD83D\u0041""#), Err(AonError::InvalidEscape(r"\uD83D\u0041".to_owned(), span(1, 13))));
+        assert_eq!(word(r#""\uDE00""#), Err(AonError::InvalidEscape(r"\uDE00".to_owned(), span(1, 7))));
+        assert_eq!(word(r#""abc"#), Err(AonError::UnexpectedEndOfFile(span(4, 4))));
+    }
+
+    #[test]
+    fn test_dialects() {
+        let lenient = |aon| Lexer::new(aon).with_options(&ParseOptions::new().dialect(Dialect::Lenient)).next().unwrap();
+        assert_eq!(lenient(r#"'it\'s "quoted"'"#).map(|token| token.kind), Ok(TokenKind::Word("it's \"quoted\"".into())));
+        assert_eq!(lenient("\"'\"").map(|token| token.kind), Ok(TokenKind::Word("'".into())));
+        assert_eq!(word("'a'"), Err(AonError::UnexpectedCharacter('\'', span(0, 1))));
+
+        let json = |aon| Lexer::new(aon).with_options(&ParseOptions::new().dialect(Dialect::Json)).next().unwrap();
+
+        for aon in ["01", "-01", "00.5"] {
+            assert_eq!(json(aon), Err(AonError::InvalidNumber(aon.to_owned(), span(0, aon.len()))));
+        }
+
+        assert_eq!(json("0.5").map(|token| token.kind), Ok(TokenKind::Number(Number::Float(0.5))));
+        assert_eq!(json("-0").map(|token| token.kind), Lexer::new("-0").next().unwrap().map(|token| token.kind));
+        assert_eq!(Lexer::new("01").next().unwrap().map(|token| token.kind), Ok(TokenKind::Number(Number::PosInt(1))));
+    }
+
+    #[test]
+    fn test_recovering() {
+        let kinds = |aon| Lexer::new(aon).recovering()
+            .map(|token| token.map(|token| token.kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(kinds(r#"[1 $ "a\qb", 2]"#), vec![
+            Ok(TokenKind::LeftBracket),
+            Ok(TokenKind::Number(Number::PosInt(1))),
+            Err(AonError::UnexpectedCharacter('$', span(3, 4))),
+            Err(AonError::InvalidEscape(r"\q".to_owned(), span(7, 9))),
+            Ok(TokenKind::Comma),
+            Ok(TokenKind::Number(Number::PosInt(2))),
+            Ok(TokenKind::RightBracket),
+            Ok(TokenKind::EOF),
+        ]);
+
+        assert_eq!(kinds("1..2 /"), vec![
+            Err(AonError::InvalidNumber("1..2".to_owned(), span(0, 4))),
+            Err(AonError::UnexpectedEndOfFile(span(6, 6))),
+            Ok(TokenKind::EOF),
+        ]);
+    }
 }
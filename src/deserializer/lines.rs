@@ -0,0 +1,185 @@
+use crate::{representation::{Value, BorrowedValue}, error::{AonError, Result}};
+
+use super::{lexer::Lexer, options::ParseOptions, parser::{Parser, unexpected}, span::Position, tokens::{self, Token, TokenKind}};
+
+// The records of a multi-document stream, one value each. A record takes a
+// line of its own, unless the value on it goes on past the end of the line,
+// in which case the record runs up to the next separator line, `---`, and can
+// span several lines. Streams may mix the two. Separator lines and records
+// holding nothing but whitespace and comments are skipped.
+//
+// Each record is parsed on its own, so a corrupt record is reported as an
+// error and the records after it are still read. Spans in the errors point
+// into the whole stream, as does the size limit, past which nothing more is
+// read.
+pub struct Documents<'a> {
+    rest: &'a str,
+    position: Position,
+    options: ParseOptions,
+}
+
+impl<'a> Documents<'a> {
+    pub fn new(aon: &'a str) -> Documents<'a> {
+        Documents {
+            rest: aon,
+            position: Position::START,
+            options: ParseOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: &ParseOptions) -> Documents<'a> {
+        self.options = options.clone();
+        self
+    }
+
+    // Parses the next record, which is blank if it holds no value, and moves
+    // past it.
+    fn next_record(&mut self) -> Option<Result<Option<BorrowedValue<'a>>>> {
+        let rest = self.rest;
+
+        if rest.is_empty() {
+            return None;
+        }
+
+        let start = self.position;
+        let mut end = rest.find('\n').map_or(rest.len(), |index| index + 1);
+        let line = &rest[..end];
+
+        let result = match is_separator(line) {
+            true => Ok(None),
+            false => match parse_record(line, start, &self.options) {
+                // A record spanning several lines runs up to the separator
+                // after it. Without one the line is all there is to it.
+                Err(error) if !matches!(error, AonError::SizeLimitExceeded(..)) => match next_separator(rest) {
+                    Some((length, separator_end)) => {
+                        end = separator_end;
+                        parse_record(&rest[..length], start, &self.options)
+                    }
+                    None => Err(error),
+                },
+                result => result,
+            },
+        };
+
+        self.position = rest[..end].chars().fold(self.position, Position::advance);
+        self.rest = &rest[end..];
+        Some(result)
+    }
+}
+
+impl Iterator for Documents<'_> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let result = self.next_record()?.transpose();
+
+            if matches!(result, Some(Err(AonError::SizeLimitExceeded(..)))) {
+                self.rest = "";
+            }
+
+            if let Some(result) = result {
+                return Some(result.map(BorrowedValue::into_owned));
+            }
+        }
+    }
+}
+
+// Where the first separator line in `aon` starts and ends.
+fn next_separator(aon: &str) -> Option<(usize, usize)> {
+    let mut length = 0;
+
+    for line in aon.split_inclusive('\n') {
+        if is_separator(line) {
+            return Some((length, length + line.len()));
+        }
+
+        length += line.len();
+    }
+
+    None
+}
+
+fn is_separator(line: &str) -> bool {
+    line.trim_end() == tokens::DOCUMENT_SEPARATOR
+}
+
+// The value in `record`, or none if it is blank.
+fn parse_record<'a>(record: &'a str, start: Position, options: &ParseOptions) -> Result<Option<BorrowedValue<'a>>> {
+    let mut parser = Parser::new(Lexer::starting_at(record, start).with_options(options)).with_options(options);
+
+    if parser.first()?.kind == TokenKind::EOF {
+        return Ok(None);
+    }
+
+    let value = parser.parse_value()?;
+
+    match parser.bump()? {
+        Token { kind: TokenKind::EOF, .. } => Ok(Some(value)),
+        other => Err(unexpected(&other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{representation::value::Number, error::AonError, deserializer::{deserialize, deserialize_many, span::position_at}};
+    use super::*;
+
+    fn values(aon: &str) -> Vec<Result<Value>> {
+        deserialize_many(aon).collect()
+    }
+
+    fn number(number: u64) -> Result<Value> {
+        Ok(Value::Number(Number::PosInt(number)))
+    }
+
+    #[test]
+    fn test_lines() {
+        let aon = "{ a: 1 }\n[2, 3]\r\n\n// a comment\n#some(4)\n  5  // trailing\n\"six\"";
+
+        assert_eq!(values(aon), vec![
+            deserialize("{ a: 1 }"),
+            deserialize("[2, 3]"),
+            deserialize("#some(4)"),
+            number(5),
+            Ok(Value::String("six".to_owned())),
+        ]);
+
+        assert_eq!(values(""), vec![]);
+        assert_eq!(values("\n  \n// nothing\n"), vec![]);
+    }
+
+    #[test]
+    fn test_separators() {
+        let aon = "{\n    a: 1,\n}\n---\n#none\n\n---\n// empty\n---\n[\n    2\n]\n---\n";
+
+        assert_eq!(values(aon), vec![deserialize("{ a: 1 }"), deserialize("#none"), deserialize("[2]")]);
+        assert_eq!(values("1\n--- \n2"), vec![number(1), number(2)]);
+    }
+
+    #[test]
+    fn test_mixed() {
+        assert_eq!(values("{a:1}\n{a:2}\n---\n{a:3}"), vec![deserialize("{a:1}"), deserialize("{a:2}"), deserialize("{a:3}")]);
+
+        let aon = "1\n[\n    2,\n    3\n]\n---\n4\n{\n    a: 5\n}\n---\n6\n";
+        assert_eq!(values(aon), vec![number(1), deserialize("[2, 3]"), number(4), deserialize("{ a: 5 }"), number(6)]);
+    }
+
+    #[test]
+    fn test_recovery() {
+        let results = values("1\n{ a: \"cut\n[1 2]\n3 4\n5\n$\n6");
+
+        assert_eq!(results.len(), 7);
+        assert_eq!(results[0], number(1));
+        assert!(matches!(results[1], Err(AonError::UnexpectedEndOfFile(span)) if span.start == position_at(12, 3, 1)));
+        assert!(matches!(results[2], Err(AonError::UnexpectedToken(_, span)) if span.start == position_at(15, 3, 4)));
+        assert!(matches!(results[3], Err(AonError::UnexpectedToken(TokenKind::Number(Number::PosInt(4)), _))));
+        assert_eq!(results[4], number(5));
+        assert!(matches!(results[5], Err(AonError::UnexpectedCharacter('$', span)) if span.start == position_at(24, 6, 1)));
+        assert_eq!(results[6], number(6));
+
+        let results = values("{\n  a: 1\n---\n{ b: 2 }\n---");
+        assert!(matches!(results[0], Err(AonError::UnexpectedEndOfFile(_))));
+        assert_eq!(results[1], deserialize("{ b: 2 }"));
+    }
+}
@@ -1,11 +1,108 @@
-use crate::{representation::Value, error::AonError};
+use std::io::Read;
+
+use serde::de::DeserializeOwned;
+
+use crate::{representation::{Value, BorrowedValue}, error::{AonError, AonErrors}};
+
+pub use options::{Dialect, DuplicateKeys, ParseOptions};
 
 pub mod cursor;
+pub mod reader;
+pub mod span;
 pub mod tokens;
 pub mod lexer;
 pub mod parser;
+pub mod cst;
+pub mod document;
+pub mod events;
+pub mod lines;
+pub mod options;
+pub mod value_deserializer;
 
 pub fn deserialize(aon: &str) -> Result<Value, AonError> {
-    let tokens = lexer::tokenize(aon)?;
-    parser::parse_tokens(&tokens)
+    deserialize_borrowed(aon).map(BorrowedValue::into_owned)
+}
+
+// Parses `aon` without copying strings that can be borrowed from it.
+pub fn deserialize_borrowed(aon: &str) -> Result<BorrowedValue<'_>, AonError> {
+    parser::parse(lexer::Lexer::new(aon))
+}
+
+// Parses standard JSON, reading objects with a string `type` back as unions.
+pub fn deserialize_json(json: &str) -> Result<Value, AonError> {
+    deserialize_with(json, &ParseOptions::new().dialect(Dialect::Json))
+}
+
+// Parses `aon` within the limits set in `options`.
+pub fn deserialize_with(aon: &str, options: &ParseOptions) -> Result<Value, AonError> {
+    parser::parse_with(lexer::Lexer::new(aon).with_options(options), options).map(BorrowedValue::into_owned)
+}
+
+// Parses `aon` past any errors in it, returning every error found along with
+// what could be made of the value. Parts that could not be parsed are null,
+// and `AonErrors::placeholders` tells where they are.
+pub fn deserialize_partial(aon: &str) -> (Value, AonErrors) {
+    deserialize_partial_with(aon, &ParseOptions::default())
+}
+
+// Parses `aon` past any errors in it like `deserialize_partial`, within the
+// limits set in `options`. Going past a limit is reported as an error too.
+pub fn deserialize_partial_with(aon: &str, options: &ParseOptions) -> (Value, AonErrors) {
+    let (value, errors) = parser::parse_recovering_with(lexer::Lexer::new(aon).with_options(options).recovering(), options);
+    (value.into_owned(), errors)
+}
+
+// Parses AON as it is read from `reader`, without reading all of it into
+// memory first. Reading stops at the first error.
+pub fn deserialize_reader(reader: impl Read) -> Result<Value, AonError> {
+    parser::parse(lexer::Lexer::from_reader(reader)).map(BorrowedValue::into_owned)
+}
+
+// Parses AON read from `reader` within the limits set in `options`. Reading
+// stops once the input is larger than allowed.
+pub fn deserialize_reader_with(reader: impl Read, options: &ParseOptions) -> Result<Value, AonError> {
+    parser::parse_with(lexer::Lexer::from_reader(reader).with_options(options), options).map(BorrowedValue::into_owned)
+}
+
+// Parses the records of a multi-document stream, one value at a time.
+pub fn deserialize_many(aon: &str) -> lines::Documents<'_> {
+    lines::Documents::new(aon)
+}
+
+// Parses the records of a multi-document stream like `deserialize_many`,
+// each within the limits set in `options`. The size limit is on the stream
+// as a whole.
+pub fn deserialize_many_with<'a>(aon: &'a str, options: &ParseOptions) -> lines::Documents<'a> {
+    lines::Documents::new(aon).with_options(options)
+}
+
+// Reports `aon` as a sequence of events, without building a value tree.
+pub fn parse_events(aon: &str) -> events::Events<'_, lexer::Lexer<'_>> {
+    parse_events_with(aon, &ParseOptions::default())
+}
+
+// Reports `aon` as a sequence of events like `parse_events`, within the
+// limits set in `options`.
+pub fn parse_events_with<'a>(aon: &'a str, options: &ParseOptions) -> events::Events<'a, lexer::Lexer<'a>> {
+    events::Events::new(lexer::Lexer::new(aon).with_options(options)).with_options(options)
+}
+
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, AonError> {
+    T::deserialize(value)
+}
+
+pub fn from_str<T: DeserializeOwned>(aon: &str) -> Result<T, AonError> {
+    from_value(deserialize(aon)?)
+}
+
+pub fn from_str_with<T: DeserializeOwned>(aon: &str, options: &ParseOptions) -> Result<T, AonError> {
+    from_value(deserialize_with(aon, options)?)
+}
+
+pub fn from_reader<T: DeserializeOwned>(reader: impl Read) -> Result<T, AonError> {
+    from_value(deserialize_reader(reader)?)
+}
+
+pub fn from_reader_with<T: DeserializeOwned>(reader: impl Read, options: &ParseOptions) -> Result<T, AonError> {
+    from_value(deserialize_reader_with(reader, options)?)
 }
\ No newline at end of file
@@ -0,0 +1,278 @@
+// Settings for how a document is parsed. The limits bound the time and
+// memory parsing untrusted input can take, and the stack the parser's
+// recursion needs. Only the depth is limited by default, as running out of
+// stack aborts the process instead of returning an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOptions {
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) max_size: Option<usize>,
+    pub(crate) max_string_length: Option<usize>,
+    pub(crate) max_elements: Option<usize>,
+    pub(crate) duplicate_keys: DuplicateKeys,
+    pub(crate) dialect: Dialect,
+}
+
+// What to do with a key written more than once in the same struct, be it
+// directly or through dot notation, as in `a.b: 1, a: { b: 2 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    // Keep every field, in the order they were written.
+    #[default]
+    Allow,
+    Error,
+    FirstWins,
+    // The last value replaces the first, where the first was written.
+    LastWins,
+    // Structs are merged field by field, any other duplicate is an error.
+    MergeStructs,
+}
+
+// How closely the text has to follow the spec, or whether it is JSON. The
+// first two allow trailing commas and comments between values and fields, as
+// the spec does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    // Strings in double quotes only, and no comments inside a dotted key or
+    // between a `#` and its variant. What files checked in should be held to.
+    #[default]
+    Strict,
+    // Also strings in single quotes, and bare words as strings, as in
+    // `{ mode: fast }`. What hand edited files tend to contain.
+    Lenient,
+    // Standard JSON, in which an object with a string `type` is read back as
+    // a union. The other fields are its payload, or its values if they are
+    // all in a `values` array, as in `{ "type": "point", "values": [1, 2] }`.
+    Json,
+}
+
+// Deep enough for any document written by hand, shallow enough for the
+// smallest stack a thread is given.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_size: None,
+            max_string_length: None,
+            max_elements: None,
+            duplicate_keys: DuplicateKeys::default(),
+            dialect: Dialect::default(),
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // How many structs, arrays and unions deep values may be nested,
+    // `DEFAULT_MAX_DEPTH` unless set.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    // How many bytes of input are read at most.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    // How many bytes long a string, key or variant name may be, after its
+    // escapes have been read.
+    pub fn max_string_length(mut self, max_string_length: usize) -> Self {
+        self.max_string_length = Some(max_string_length);
+        self
+    }
+
+    // How many values an array or tuple, or fields a struct, may hold.
+    pub fn max_elements(mut self, max_elements: usize) -> Self {
+        self.max_elements = Some(max_elements);
+        self
+    }
+
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{
+        error::AonError,
+        deserializer::{
+            deserialize, deserialize_with, deserialize_reader_with, deserialize_partial, deserialize_partial_with, deserialize_many,
+            deserialize_many_with, parse_events, parse_events_with, from_str, from_str_with, from_reader, from_reader_with, cst::SyntaxTree, document::Document, span::{position, position_at, span, Span}, tokens::TokenKind,
+        },
+    };
+    use super::*;
+
+    #[test]
+    fn test_max_depth() {
+        let options = ParseOptions::new().max_depth(2);
+
+        assert_eq!(deserialize_with("[{ a: 1 }]", &options), deserialize("[{ a: 1 }]"));
+        assert_eq!(deserialize_with("[#a { b: [1] }]", &options), Err(AonError::DepthLimitExceeded(2, span(9, 10))));
+        assert_eq!(deserialize_with("1", &ParseOptions::new().max_depth(0)), deserialize("1"));
+
+        // Far deeper than the stack would allow without the limit.
+        let aon = "[".repeat(1_000_000);
+        assert_eq!(deserialize_with(&aon, &ParseOptions::new().max_depth(64)), Err(AonError::DepthLimitExceeded(64, span(64, 65))));
+    }
+
+    #[test]
+    fn test_default_max_depth() {
+        let limit = AonError::DepthLimitExceeded(DEFAULT_MAX_DEPTH, span(DEFAULT_MAX_DEPTH, DEFAULT_MAX_DEPTH + 1));
+        let aon = "[".repeat(200_000) + &"]".repeat(200_000);
+
+        // Every front end stops at the limit instead of running out of stack.
+        assert_eq!(deserialize(&aon), Err(limit.clone()));
+        assert_eq!(from_str::<Vec<u8>>(&aon), Err(limit.clone()));
+        assert_eq!(from_reader::<Vec<u8>>(aon.as_bytes()), Err(limit.clone()));
+        assert_eq!(SyntaxTree::parse(&aon).err(), Some(limit.clone()));
+        assert_eq!(deserialize_many(&aon).next(), Some(Err(limit.clone())));
+        assert_eq!(parse_events(&aon).find_map(Result::err), Some(limit.clone()));
+        assert_eq!(deserialize_partial(&aon).1.iter().next(), Some(&limit));
+
+        let nested = "[".repeat(DEFAULT_MAX_DEPTH) + &"]".repeat(DEFAULT_MAX_DEPTH);
+        assert!(deserialize(&nested).is_ok());
+    }
+
+    #[test]
+    fn test_max_size() {
+        let options = ParseOptions::new().max_size(6);
+
+        assert_eq!(deserialize_with("[1, 2]", &options), deserialize("[1, 2]"));
+        assert_eq!(deserialize_with("[1, 2] ", &options), Err(AonError::SizeLimitExceeded(6, span(6, 6))));
+        assert_eq!(deserialize_with("\"abcdefgh\"", &options), Err(AonError::SizeLimitExceeded(6, span(6, 6))));
+
+        // Reading stops at the limit, even if the input never ends.
+        let error = deserialize_reader_with(io::repeat(b' '), &ParseOptions::new().max_size(1000));
+        assert_eq!(error, Err(AonError::SizeLimitExceeded(1000, span(1000, 1000))));
+    }
+
+    #[test]
+    fn test_max_string_length() {
+        let options = ParseOptions::new().max_string_length(4);
+
+        assert_eq!(deserialize_with("{ abcd: \"\\u{1F600}\" }", &options), deserialize("{ abcd: \"😀\" }"));
+        assert_eq!(deserialize_with("[\"abcde\"]", &options), Err(AonError::StringLimitExceeded(4, span(1, 8))));
+        assert_eq!(deserialize_with("{ abcde: 1 }", &options), Err(AonError::StringLimitExceeded(4, span(2, 7))));
+        assert_eq!(deserialize_with("#abcde", &options), Err(AonError::StringLimitExceeded(4, span(1, 6))));
+    }
+
+    #[test]
+    fn test_max_elements() {
+        let options = ParseOptions::new().max_elements(2);
+
+        assert_eq!(deserialize_with("[[1, 2], { a: 1, b.c: 2 }, ]", &options), deserialize("[[1, 2], { a: 1, b.c: 2 }]"));
+        assert_eq!(deserialize_with("[1, 2, 3]", &options), Err(AonError::ElementLimitExceeded(2, span(7, 8))));
+        assert_eq!(deserialize_with("{ a: 1, b: 2, c: 3 }", &options), Err(AonError::ElementLimitExceeded(2, span(14, 15))));
+        assert_eq!(deserialize_with("#a(1, 2, 3)", &options), Err(AonError::ElementLimitExceeded(2, span(9, 10))));
+        assert_eq!(
+            deserialize_with("[1, 2, 3]", &options).unwrap_err().to_string(),
+            "1:8: number of elements exceeds the limit of 2",
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let with = |aon, duplicate_keys| deserialize_with(aon, &ParseOptions::new().duplicate_keys(duplicate_keys));
+        let aon = "{ a: 1, b: 2, a: 3 }";
+
+        assert_eq!(with(aon, DuplicateKeys::Allow), deserialize(aon));
+        assert_eq!(with(aon, DuplicateKeys::FirstWins), deserialize("{ a: 1, b: 2 }"));
+        assert_eq!(with(aon, DuplicateKeys::LastWins), deserialize("{ a: 3, b: 2 }"));
+        assert_eq!(with(aon, DuplicateKeys::Error), Err(AonError::DuplicateKey("a".to_owned(), span(14, 15), position(2))));
+        assert_eq!(with(aon, DuplicateKeys::MergeStructs).unwrap_err().to_string(), "1:15: duplicate key 'a', first written at 1:3");
+        assert_eq!(with("#x { a: 1, a: 2 }", DuplicateKeys::LastWins), deserialize("#x { a: 2 }"));
+
+        // Dot notation onto a value that is not a struct.
+        let aon = "{ a: 1, a.b: 2 }";
+        assert_eq!(with(aon, DuplicateKeys::FirstWins), deserialize("{ a: 1 }"));
+        assert_eq!(with(aon, DuplicateKeys::LastWins), deserialize("{ a.b: 2 }"));
+        assert!(matches!(with(aon, DuplicateKeys::Error), Err(AonError::KeyCollision(..))));
+    }
+
+    #[test]
+    fn test_merge_structs() {
+        let with = |aon, duplicate_keys| deserialize_with(aon, &ParseOptions::new().duplicate_keys(duplicate_keys));
+        let aon = "{ a.b: 1, a: { c: 2, d.e: 3 }, a.d.f: 4 }";
+
+        assert_eq!(with(aon, DuplicateKeys::MergeStructs), deserialize("{ a: { b: 1, c: 2, d: { e: 3, f: 4 } } }"));
+        assert_eq!(with(aon, DuplicateKeys::Error), Err(AonError::DuplicateKey("a".to_owned(), span(10, 11), position(2))));
+        assert_eq!(with(aon, DuplicateKeys::LastWins), deserialize("{ a: { c: 2, d: { e: 3, f: 4 } } }"));
+
+        // Both occurrences are pointed at, even inside the structs merged.
+        let aon = "{ a: { b: 1 }, a: { b: 2 } }";
+        assert_eq!(with(aon, DuplicateKeys::MergeStructs), Err(AonError::DuplicateKey("b".to_owned(), span(20, 21), position(7))));
+
+        let aon = "{ a: { b: 1 }, a.b: 2 }";
+        assert_eq!(with(aon, DuplicateKeys::Error), Err(AonError::DuplicateKey("a.b".to_owned(), span(15, 18), position(7))));
+    }
+
+    #[test]
+    fn test_dialect() {
+        let lenient = ParseOptions::new().dialect(Dialect::Lenient);
+
+        let aon = "{ mode: fast, 'a b': 'it\\'s', list: [null, true, nul], }";
+        assert_eq!(deserialize_with(aon, &lenient), deserialize(r#"{ mode: "fast", "a b": "it's", list: [null, true, "nul"] }"#));
+        assert_eq!(deserialize("{ mode: fast }"), Err(AonError::InvalidKeyword("fast".to_owned(), span(8, 12))));
+        assert_eq!(deserialize("'a'"), Err(AonError::UnexpectedCharacter('\'', span(0, 1))));
+
+        // Comments and trailing commas between fields are fine either way.
+        let aon = "{ a: 1, /* b */ b: 2, // c\n}";
+        assert_eq!(deserialize(aon), deserialize("{ a: 1, b: 2 }"));
+
+        let comment = |text: &str, start, end| Err(AonError::UnexpectedToken(TokenKind::BlockComment(text.to_owned().into()), span(start, end)));
+        assert_eq!(deserialize("{ a./* x */b: 1 }"), comment(" x ", 4, 11));
+        assert_eq!(deserialize("{ a /* x */.b: 1 }"), comment(" x ", 4, 11));
+        assert_eq!(deserialize("#/* x */some(1)"), comment(" x ", 1, 8));
+        assert_eq!(deserialize_with("{ a./* x */b: 1 }", &lenient), deserialize("{ a.b: 1 }"));
+        assert_eq!(deserialize_with("#/* x */some(1)", &lenient), deserialize("#some(1)"));
+    }
+
+    #[test]
+    fn test_front_ends() {
+        let options = ParseOptions::new().max_depth(2).max_elements(2);
+        let error = |start, end| AonError::DepthLimitExceeded(2, span(start, end));
+
+        assert_eq!(from_str_with::<Vec<Vec<u8>>>("[[1, 2]]", &options), Ok(vec![vec![1, 2]]));
+        assert_eq!(from_str_with::<Vec<Vec<Vec<u8>>>>("[[[1]]]", &options), Err(error(2, 3)));
+        assert_eq!(from_reader_with::<Vec<u8>>("[1, 2, 3]".as_bytes(), &options), Err(AonError::ElementLimitExceeded(2, span(7, 8))));
+
+        assert_eq!(SyntaxTree::parse_with("[#a { b: 1 }]", &options).map(|tree| tree.to_string()), Ok("[#a { b: 1 }]".to_owned()));
+        assert_eq!(SyntaxTree::parse_with("[#a { b: [1] }]", &options).map(|_| ()), Err(error(9, 10)));
+        assert_eq!(SyntaxTree::parse_with("{ a: 1, b: 2, c: 3 }", &options).map(|_| ()), Err(AonError::ElementLimitExceeded(2, span(14, 15))));
+        assert_eq!(Document::parse_with("[[[1]]]", &options).map(|_| ()), Err(error(2, 3)));
+
+        let (value, errors) = deserialize_partial_with("[[1], [[2]]]", &options);
+        assert_eq!(value, deserialize("[[1], [null]]").unwrap());
+        assert_eq!(errors.iter().collect::<Vec<_>>(), [&error(7, 8)]);
+
+        let results = deserialize_many_with("[1]\n[[[2]]]\n3", &options).collect::<Vec<_>>();
+        let second = Span::new(position_at(6, 2, 3), position_at(7, 2, 4));
+        assert_eq!(results, [deserialize("[1]"), Err(AonError::DepthLimitExceeded(2, second)), deserialize("3")]);
+
+        // Nothing is read past the size limit of the stream.
+        let results = deserialize_many_with("1\n2\n3\n4", &ParseOptions::new().max_size(4)).collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[2], Err(AonError::SizeLimitExceeded(4, _))));
+
+        let events = |aon| parse_events_with(aon, &options).collect::<Result<Vec<_>, _>>().map(|events| events.len());
+        assert_eq!(events("[#a { b: 1 }, [2]]"), Ok(11));
+        assert_eq!(events("[#a { b: [1] }]"), Err(error(9, 10)));
+        assert_eq!(events("{ a.b.c: 1, d: 2 }"), Ok(12));
+        assert_eq!(events("#a(1, 2, 3)"), Err(AonError::ElementLimitExceeded(2, span(9, 10))));
+    }
+}
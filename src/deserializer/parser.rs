@@ -1,30 +1,973 @@
-use crate::{representation::Value, error::{AonError, Result}};
+use std::borrow::Cow;
 
-use super::tokens::Token;
+use crate::{representation::{Value, BorrowedValue, BorrowedPayload}, error::{AonError, AonErrors, Result}};
 
-pub fn parse_tokens(tokens: &Vec<Token>) -> Result<Value> {
-    parse_primitive(&mut tokens.clone())
+use super::{options::{Dialect, DuplicateKeys, ParseOptions}, span::Span, tokens::{self, Token, TokenKind}};
+
+pub fn parse_tokens(tokens: &[Token]) -> Result<Value> {
+    parse(tokens.iter().cloned().map(Ok)).map(BorrowedValue::into_owned)
+}
+
+// Parses a value from a stream of tokens, such as a `Lexer`, looking no
+// further ahead than the next token. Errors in the stream end the parse.
+// Strings in the value are borrowed from the tokens wherever they are.
+pub fn parse<'a>(tokens: impl Iterator<Item = Result<Token<'a>>>) -> Result<BorrowedValue<'a>> {
+    parse_with(tokens, &ParseOptions::default())
+}
+
+// Parses a value like `parse`, enforcing the limits in `options` that apply
+// to its structure. Those on the text itself are up to the lexer.
+pub fn parse_with<'a>(tokens: impl Iterator<Item = Result<Token<'a>>>, options: &ParseOptions) -> Result<BorrowedValue<'a>> {
+    parse_into(tokens, options, ())
+}
+
+// Parses a value like `parse_with`, telling `sink` how the tokens read make
+// up the value along the way.
+pub(crate) fn parse_into<'a, S: Sink<'a>>(tokens: impl Iterator<Item = Result<Token<'a>>>, options: &ParseOptions, sink: S) -> Result<BorrowedValue<'a>> {
+    let mut parser = Parser::with_sink(tokens, sink).with_options(options);
+    let value = parser.parse_value()?;
+
+    match parser.bump()? {
+        Token { kind: TokenKind::EOF, .. } => Ok(value),
+        other => Err(unexpected(&other)),
+    }
 }
 
-fn parse_list(tokens: &mut Vec<Token>) -> Result<Value> {
-    Ok(Value::Null)
+// Parses a value like `parse`, but carries on past errors to find as many of
+// them as it can. After an error the parser skips ahead to the next comma or
+// closing delimiter, and values it could not parse are left as null. Where
+// those nulls are is reported by `AonErrors::placeholders`.
+pub fn parse_recovering<'a>(tokens: impl Iterator<Item = Result<Token<'a>>>) -> (BorrowedValue<'a>, AonErrors) {
+    parse_recovering_with(tokens, &ParseOptions::default())
 }
 
-fn parse_primitive(tokens: &mut Vec<Token>) -> Result<Value> {
-    match first(&tokens) {
-        Token::Word(string) => Ok(Value::String(string.to_owned())),
-        Token::Number(number) => Ok(Value::Number(number.to_owned())),
-        Token::Bool(boolean) => Ok(Value::Bool(*boolean)),
-        Token::Null => Ok(Value::Null),
-        Token::EOF => Err(AonError::UnexpectedEndOfFile),
-        other => Err(AonError::UnexpectedToken(other.clone())),
+// Parses a value like `parse_recovering`, within the limits in `options`.
+pub fn parse_recovering_with<'a>(tokens: impl Iterator<Item = Result<Token<'a>>>, options: &ParseOptions) -> (BorrowedValue<'a>, AonErrors) {
+    let mut parser = Parser::new(tokens).with_options(options);
+    parser.errors = Some(vec![]);
+
+    // Recording errors cannot fail while recovering from them.
+    let span = parser.first().map(|token| token.span).ok();
+    let value = parser.parse_value().unwrap_or_else(|error| {
+        let _ = parser.record(error);
+        parser.placeholders.extend(span);
+        BorrowedValue::Null
+    });
+
+    // Whatever is left after the value is reported once and skipped.
+    if let Ok(token) = parser.first() {
+        if token.kind != TokenKind::EOF {
+            let error = unexpected(token);
+            let _ = parser.record(error);
+        }
+    }
+
+    while parser.bump().is_ok_and(|token| token.kind != TokenKind::EOF) {}
+
+    (value, AonErrors::new(parser.errors.unwrap_or_default()).with_placeholders(parser.placeholders))
+}
+
+// The parts of a value made up of more than a single token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NodeKind {
+    Struct,
+    // A field of a struct, its key, colon and value.
+    Entry,
+    Array,
+    Union,
+    // The values of a union in parentheses.
+    Tuple,
+}
+
+// Follows the parser through the tokens it reads, for those that need more
+// than the value, like the syntax tree. Every node is started before its
+// first token is taken and finished after its last one.
+pub(crate) trait Sink<'a> {
+    // Each token as it is read from the stream, comments included.
+    fn read(&mut self, _token: &Token<'a>) {}
+    // The parser took the next token that is not a comment.
+    fn take(&mut self) {}
+    fn start(&mut self, _kind: NodeKind) {}
+    fn finish(&mut self) {}
+}
+
+impl Sink<'_> for () {}
+
+impl<'a, S: Sink<'a> + ?Sized> Sink<'a> for &mut S {
+    fn read(&mut self, token: &Token<'a>) {
+        (**self).read(token)
+    }
+
+    fn take(&mut self) {
+        (**self).take()
+    }
+
+    fn start(&mut self, kind: NodeKind) {
+        (**self).start(kind)
+    }
+
+    fn finish(&mut self) {
+        (**self).finish()
     }
 }
 
-fn first(tokens: &Vec<Token>) -> &Token {
-    tokens.get(0).unwrap_or(&Token::EOF)
+pub(crate) struct Parser<'a, I, S = ()> {
+    tokens: I,
+    sink: S,
+    peeked: Option<Token<'a>>,
+    // Where the stream ended up so far, for streams that stop without EOF.
+    end: Span,
+    // The errors recovered from so far, when recovering from errors at all.
+    errors: Option<Vec<AonError>>,
+    // The spans of the values left as null after an error.
+    placeholders: Vec<Span>,
+    // Whether the lexer failed right before the last token it returned.
+    after_lexer_error: bool,
+    // The comment right before the last token taken from the stream, if any.
+    comment: Option<Token<'a>>,
+    options: ParseOptions,
+    // How many structs, arrays and unions the parser is inside of.
+    depth: usize,
+    // The spans of the keys of the struct parsed last.
+    struct_spans: KeySpans,
 }
 
-fn is_eof(tokens: &Vec<Token>) -> bool {
-    tokens.is_empty() || first(tokens) == &Token::EOF
+impl<'a, I: Iterator<Item = Result<Token<'a>>>> Parser<'a, I> {
+    pub(crate) fn new(tokens: I) -> Parser<'a, I> {
+        Parser::with_sink(tokens, ())
+    }
+}
+
+impl<'a, I: Iterator<Item = Result<Token<'a>>>, S: Sink<'a>> Parser<'a, I, S> {
+    pub(crate) fn with_sink(tokens: I, sink: S) -> Parser<'a, I, S> {
+        Parser {
+            tokens,
+            sink,
+            peeked: None,
+            end: Span::EMPTY,
+            errors: None,
+            placeholders: vec![],
+            after_lexer_error: false,
+            comment: None,
+            options: ParseOptions::default(),
+            depth: 0,
+            struct_spans: KeySpans::default(),
+        }
+    }
+
+    pub(crate) fn with_options(mut self, options: &ParseOptions) -> Parser<'a, I, S> {
+        self.options = options.clone();
+        self
+    }
+
+    pub(crate) fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    // Comments carry no meaning for the value tree, so they are skipped
+    // wherever the parser takes the next token.
+    fn next_token(&mut self) -> Result<Token<'a>> {
+        let mut after_lexer_error = false;
+        let mut comment = None;
+
+        loop {
+            let token = match (self.tokens.next().transpose(), &mut self.errors) {
+                (Err(error), Some(errors)) => {
+                    errors.push(error);
+                    after_lexer_error = true;
+                    continue;
+                }
+                (token, _) => token?,
+            };
+
+            if let Some(token) = &token {
+                self.sink.read(token);
+            }
+
+            match token {
+                Some(token @ Token { kind: TokenKind::Comment(_) | TokenKind::BlockComment(_), .. }) => comment = Some(token),
+                Some(token) => {
+                    self.end = Span::new(token.span.end, token.span.end);
+                    self.after_lexer_error = after_lexer_error;
+                    self.comment = comment;
+                    return Ok(token);
+                }
+                // Token streams from the lexer always end in EOF, this only
+                // guards against hand built ones that do not.
+                None => return Ok(Token::new(TokenKind::EOF, self.end)),
+            }
+        }
+    }
+
+    pub(crate) fn first(&mut self) -> Result<&Token<'a>> {
+        let token = match self.peeked.take() {
+            Some(token) => token,
+            None => self.next_token()?,
+        };
+
+        Ok(self.peeked.insert(token))
+    }
+
+    pub(crate) fn bump(&mut self) -> Result<Token<'a>> {
+        let token = match self.peeked.take() {
+            Some(token) => token,
+            None => self.next_token()?,
+        };
+
+        self.sink.take();
+        Ok(token)
+    }
+
+    pub(crate) fn eat(&mut self, expected: &TokenKind) -> Result<bool> {
+        if &self.first()?.kind == expected {
+            self.bump()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub(crate) fn expect(&mut self, expected: &TokenKind) -> Result<Token<'a>> {
+        match self.bump()? {
+            token if &token.kind == expected => Ok(token),
+            other => Err(self.reject(other)),
+        }
+    }
+
+    // The error for a token that does not belong where it is. The token is
+    // put back, so that recovering from the error can look at it.
+    fn reject(&mut self, token: Token<'a>) -> AonError {
+        let error = unexpected(&token);
+        self.peeked = Some(token);
+        error
+    }
+
+    // Records `error`, or returns it when not recovering from errors.
+    fn record(&mut self, error: AonError) -> Result<()> {
+        let Some(errors) = &mut self.errors else {
+            return Err(error);
+        };
+
+        // A token out of place right after text the lexer could not read is
+        // most likely there because of it, and an error found again by each
+        // enclosing container, like a missing delimiter at the end of the
+        // input, only needs reporting once.
+        let cascading = self.after_lexer_error && matches!(error, AonError::UnexpectedToken(..) | AonError::UnexpectedEndOfFile(_));
+
+        if !cascading && errors.last() != Some(&error) {
+            errors.push(error);
+        }
+
+        Ok(())
+    }
+
+    // Records `error` and skips to the next comma or closing delimiter that
+    // is not nested any deeper.
+    fn recover(&mut self, error: AonError) -> Result<()> {
+        self.record(error)?;
+
+        let mut depth = 0usize;
+
+        loop {
+            match self.first()?.kind {
+                TokenKind::EOF => return Ok(()),
+                TokenKind::Comma | TokenKind::RightBrace | TokenKind::RightBracket | TokenKind::RightParen if depth == 0 => return Ok(()),
+                TokenKind::RightBrace | TokenKind::RightBracket | TokenKind::RightParen => depth -= 1,
+                TokenKind::LeftBrace | TokenKind::LeftBracket | TokenKind::LeftParen => depth += 1,
+                _ => {}
+            }
+
+            self.bump()?;
+        }
+    }
+
+    pub(crate) fn parse_value(&mut self) -> Result<BorrowedValue<'a>> {
+        let token = self.first()?;
+        let span = token.span;
+
+        let parse: fn(&mut Self) -> Result<BorrowedValue<'a>> = match token.kind {
+            TokenKind::LeftBrace => |parser| parser.parse_struct().map(|fields| match parser.options.dialect {
+                Dialect::Json => from_json_object(fields),
+                _ => BorrowedValue::Struct(fields),
+            }),
+            TokenKind::LeftBracket => Self::parse_list,
+            TokenKind::Hash => Self::parse_union,
+            _ => return self.parse_primitive(),
+        };
+
+        // The depth is checked before going any deeper, as each level takes
+        // another call on the stack.
+        if let Some(max_depth) = self.options.max_depth.filter(|max_depth| self.depth >= *max_depth) {
+            return Err(AonError::DepthLimitExceeded(max_depth, span));
+        }
+
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_struct(&mut self) -> Result<Vec<(Cow<'a, str>, BorrowedValue<'a>)>> {
+        let mut fields = vec![];
+        let mut spans = KeySpans::default();
+        self.parse_delimited(&TokenKind::LeftBrace, &TokenKind::RightBrace, |parser| parser.parse_field(&mut fields, &mut spans))?;
+        self.struct_spans = spans;
+        Ok(fields)
+    }
+
+    fn parse_field(&mut self, fields: &mut Vec<(Cow<'a, str>, BorrowedValue<'a>)>, spans: &mut KeySpans) -> Result<()> {
+        self.sink.start(NodeKind::Entry);
+        let (path, span) = self.parse_key_path()?;
+        let policy = self.options.duplicate_keys;
+
+        let value = match self.expect(&TokenKind::Colon).and_then(|_| self.parse_value()) {
+            Ok(value) => value,
+            // The key is kept with a placeholder, in case the value is
+            // recovered from.
+            Err(error) => {
+                let _ = insert_field(fields, spans, (&path, span), 0, BorrowedValue::Null, KeySpans::default(), policy);
+                self.placeholders.push(span);
+                return Err(error);
+            }
+        };
+
+        self.sink.finish();
+
+        // A struct value is the last struct parsed, the spans of its keys are
+        // needed in case it is merged with another.
+        let value_spans = match value {
+            BorrowedValue::Struct(_) => std::mem::take(&mut self.struct_spans),
+            _ => KeySpans::default(),
+        };
+
+        insert_field(fields, spans, (&path, span), 0, value, value_spans, policy)
+    }
+
+    // Parses comma separated items between `open` and `close`. An error in
+    // an item or a missing comma is recovered from at the next comma, unless
+    // the delimiters around it are what is broken.
+    fn parse_delimited(&mut self, open: &TokenKind, close: &TokenKind, mut parse_item: impl FnMut(&mut Self) -> Result<()>) -> Result<()> {
+        self.sink.start(match open {
+            TokenKind::LeftBrace => NodeKind::Struct,
+            TokenKind::LeftBracket => NodeKind::Array,
+            _ => NodeKind::Tuple,
+        });
+
+        self.expect(open)?;
+        let mut count = 0;
+
+        while !self.eat(close)? {
+            count += 1;
+
+            if let Some(max_elements) = self.options.max_elements.filter(|max_elements| count > *max_elements) {
+                return Err(AonError::ElementLimitExceeded(max_elements, self.first()?.span));
+            }
+
+            if let Err(error) = parse_item(self) {
+                self.recover(error)?;
+            } else if self.first()?.kind != TokenKind::Comma && &self.first()?.kind != close {
+                let error = unexpected(self.first()?);
+                self.recover(error)?;
+            }
+
+            if !self.eat(&TokenKind::Comma)? {
+                // Only short of the closing delimiter when recovering.
+                self.eat(close)?;
+                break;
+            }
+
+            // JSON has no trailing commas.
+            if self.options.dialect == Dialect::Json && &self.first()?.kind == close {
+                let error = unexpected(self.first()?);
+                self.recover(error)?;
+            }
+        }
+
+        self.sink.finish();
+        Ok(())
+    }
+
+    // A key may be written in dot notation, `a.b.c`, as a shorthand for
+    // nested structs. The segments are returned in order along with the span
+    // of the whole path. A quoted segment is one key, dots and all.
+    pub(crate) fn parse_key_path(&mut self) -> Result<(Vec<Cow<'a, str>>, Span)> {
+        // JSON keys are strings, taken as they are.
+        if self.options.dialect == Dialect::Json {
+            return match self.bump()? {
+                Token { kind: TokenKind::Word(key), span } => Ok((vec![key], span)),
+                other => Err(self.reject(other)),
+            };
+        }
+
+        let (key, start) = self.parse_name()?;
+        let mut path = vec![key];
+        let mut span = start;
+
+        while self.eat(&TokenKind::Dot)? {
+            self.reject_comment()?;
+            let (key, end) = self.parse_name()?;
+            self.reject_comment()?;
+            path.push(key);
+            span = start.to(end);
+        }
+
+        Ok((path, span))
+    }
+
+    // Keys and union variants can be written as identifiers or strings. A
+    // keyword in their place is taken to be the name it is spelled as.
+    pub(crate) fn parse_name(&mut self) -> Result<(Cow<'a, str>, Span)> {
+        let Token { kind, span } = self.bump()?;
+
+        match kind {
+            TokenKind::Word(name) | TokenKind::Ident(name) => Ok((name, span)),
+            TokenKind::Null => Ok((Cow::Borrowed(tokens::NULL), span)),
+            TokenKind::Bool(bool) => Ok((Cow::Borrowed(if bool { tokens::TRUE } else { tokens::FALSE }), span)),
+            other => Err(self.reject(Token::new(other, span))),
+        }
+    }
+
+    fn parse_union(&mut self) -> Result<BorrowedValue<'a>> {
+        self.sink.start(NodeKind::Union);
+        self.expect(&TokenKind::Hash)?;
+        let (variant, _) = self.parse_name()?;
+        self.reject_comment()?;
+
+        // The payload is either named fields in braces, positional values in
+        // parentheses or left out entirely.
+        let payload = match self.first()?.kind {
+            TokenKind::LeftBrace => BorrowedPayload::Struct(self.parse_struct()?),
+            TokenKind::LeftParen => BorrowedPayload::Tuple(self.parse_values(&TokenKind::LeftParen, &TokenKind::RightParen)?),
+            _ => BorrowedPayload::Unit,
+        };
+
+        self.sink.finish();
+        Ok(BorrowedValue::Union(variant, payload))
+    }
+
+    fn parse_list(&mut self) -> Result<BorrowedValue<'a>> {
+        Ok(BorrowedValue::Array(self.parse_values(&TokenKind::LeftBracket, &TokenKind::RightBracket)?))
+    }
+
+    fn parse_values(&mut self, open: &TokenKind, close: &TokenKind) -> Result<Vec<BorrowedValue<'a>>> {
+        let mut values = vec![];
+
+        self.parse_delimited(open, close, |parser| {
+            let span = parser.first().map(|token| token.span).ok();
+
+            match parser.parse_value() {
+                Ok(value) => {
+                    values.push(value);
+                    Ok(())
+                }
+                Err(error) => {
+                    values.push(BorrowedValue::Null);
+                    parser.placeholders.extend(span);
+                    Err(error)
+                }
+            }
+        })?;
+
+        Ok(values)
+    }
+
+    // In the strict dialect comments go between values and fields, not inside
+    // the names they are made of. Checked right after the token it came before.
+    pub(crate) fn reject_comment(&mut self) -> Result<()> {
+        match self.comment.take() {
+            Some(comment) if self.options.dialect == Dialect::Strict => Err(unexpected(&comment)),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn parse_primitive(&mut self) -> Result<BorrowedValue<'a>> {
+        let Token { kind, span } = self.bump()?;
+
+        match kind {
+            TokenKind::Word(string) => Ok(BorrowedValue::String(string)),
+            TokenKind::Number(number) => Ok(BorrowedValue::Number(number)),
+            TokenKind::Bool(boolean) => Ok(BorrowedValue::Bool(boolean)),
+            TokenKind::Null => Ok(BorrowedValue::Null),
+            TokenKind::Ident(string) if self.options.dialect == Dialect::Lenient => Ok(BorrowedValue::String(string)),
+            // A bare word is most likely a misspelled keyword or a string
+            // missing its quotation marks.
+            TokenKind::Ident(ident) => Err(AonError::InvalidKeyword(ident.into_owned(), span)),
+            other => Err(self.reject(Token::new(other, span))),
+        }
+    }
+}
+
+pub(crate) fn unexpected(token: &Token) -> AonError {
+    match token.kind {
+        TokenKind::EOF => AonError::UnexpectedEndOfFile(token.span),
+        _ => AonError::UnexpectedToken(token.kind.clone().into_owned(), token.span),
+    }
+}
+
+// The union a JSON object encodes, or the object as a struct. A union has a
+// string `type` and at most its payload besides, positional values under
+// `values` or named ones under `fields`, so no other object is taken for one.
+fn from_json_object<'a>(mut fields: Vec<(Cow<'a, str>, BorrowedValue<'a>)>) -> BorrowedValue<'a> {
+    let Some(index) = fields.iter().position(|(key, value)| key == tokens::UNION_TYPE && matches!(value, BorrowedValue::String(_))) else {
+        return BorrowedValue::Struct(fields);
+    };
+
+    let is_payload = |(key, value): &(Cow<str>, BorrowedValue)| match value {
+        BorrowedValue::Array(_) => key == tokens::UNION_VALUES,
+        BorrowedValue::Struct(_) | BorrowedValue::Union(..) => key == tokens::UNION_FIELDS,
+        _ => false,
+    };
+
+    if fields.len() > 2 || fields.len() == 2 && !is_payload(&fields[1 - index]) {
+        return BorrowedValue::Struct(fields);
+    }
+
+    let BorrowedValue::String(variant) = fields.remove(index).1 else {
+        unreachable!("the variant is a string")
+    };
+
+    let payload = match fields.pop() {
+        None => BorrowedPayload::Unit,
+        Some((_, BorrowedValue::Array(values))) => BorrowedPayload::Tuple(values),
+        Some((_, object)) => BorrowedPayload::Struct(object_fields(object)),
+    };
+
+    BorrowedValue::Union(variant, payload)
+}
+
+// The fields of a JSON object, which is read as a union when it looks like
+// one, even as the fields of another.
+fn object_fields(object: BorrowedValue) -> Vec<(Cow<str>, BorrowedValue)> {
+    match object {
+        BorrowedValue::Struct(fields) => fields,
+        BorrowedValue::Union(variant, payload) => {
+            let mut fields = vec![(Cow::Borrowed(tokens::UNION_TYPE), BorrowedValue::String(variant))];
+
+            match payload {
+                BorrowedPayload::Unit => {}
+                BorrowedPayload::Struct(payload) => fields.push((Cow::Borrowed(tokens::UNION_FIELDS), BorrowedValue::Struct(payload))),
+                BorrowedPayload::Tuple(values) => fields.push((Cow::Borrowed(tokens::UNION_VALUES), BorrowedValue::Array(values))),
+            }
+
+            fields
+        }
+        _ => vec![],
+    }
+}
+
+// Where the keys of a struct were written, in the same order as its fields,
+// each with the spans of the keys in the struct it holds, if any.
+#[derive(Debug, Default)]
+struct KeySpans(Vec<(Span, KeySpans)>);
+
+impl KeySpans {
+    // Duplicates are never looked for when they are allowed, so their spans
+    // are not kept either.
+    fn push(&mut self, span: Span, inner: KeySpans, policy: DuplicateKeys) {
+        if policy != DuplicateKeys::Allow {
+            self.0.push((span, inner));
+        }
+    }
+}
+
+// Inserts `value` at `path[depth..]`, creating or extending the nested structs
+// that a dotted key implies. Sibling keys sharing a prefix end up in the same
+// struct, so `a.b: 1, a.c: 2` is equivalent to `a: { b: 1, c: 2 }`. A key
+// that is already there is dealt with as `policy` says.
+fn insert_field<'a>(
+    fields: &mut Vec<(Cow<'a, str>, BorrowedValue<'a>)>,
+    spans: &mut KeySpans,
+    (path, span): (&[Cow<'a, str>], Span),
+    depth: usize,
+    value: BorrowedValue<'a>,
+    value_spans: KeySpans,
+    policy: DuplicateKeys,
+) -> Result<()> {
+    let key = &path[depth];
+
+    if depth + 1 == path.len() {
+        // Looking for the key is skipped when duplicates are allowed, which
+        // keeps inserting into large structs linear.
+        let existing = match policy {
+            DuplicateKeys::Allow => None,
+            _ => fields.iter().rposition(|(k, _)| k == key),
+        };
+
+        let Some(index) = existing else {
+            fields.push((key.clone(), value));
+            spans.push(span, value_spans, policy);
+            return Ok(());
+        };
+
+        return match (policy, &mut fields[index].1, value) {
+            (DuplicateKeys::FirstWins, _, _) => Ok(()),
+            (DuplicateKeys::LastWins, existing, value) => {
+                *existing = value;
+                spans.0[index] = (span, value_spans);
+                Ok(())
+            }
+            (DuplicateKeys::MergeStructs, BorrowedValue::Struct(inner), BorrowedValue::Struct(value)) => {
+                let inner_spans = &mut spans.0[index].1;
+
+                for ((key, value), (span, value_spans)) in value.into_iter().zip(value_spans.0) {
+                    insert_field(inner, inner_spans, (&[key], span), 0, value, value_spans, policy)?;
+                }
+
+                Ok(())
+            }
+            _ => Err(AonError::DuplicateKey(path.join("."), span, spans.0[index].0.start)),
+        };
+    }
+
+    let existing = fields.iter().rposition(|(k, _)| k == key);
+
+    match existing.map(|index| (index, &mut fields[index].1)) {
+        Some((index, BorrowedValue::Struct(inner))) => {
+            let mut untracked = KeySpans::default();
+            let inner_spans = spans.0.get_mut(index).map_or(&mut untracked, |(_, inner_spans)| inner_spans);
+            insert_field(inner, inner_spans, (path, span), depth + 1, value, value_spans, policy)
+        }
+        Some(_) if policy == DuplicateKeys::FirstWins => Ok(()),
+        Some((index, existing)) if policy == DuplicateKeys::LastWins => {
+            let mut inner = vec![];
+            let mut inner_spans = KeySpans::default();
+            insert_field(&mut inner, &mut inner_spans, (path, span), depth + 1, value, value_spans, policy)?;
+            *existing = BorrowedValue::Struct(inner);
+            spans.0[index] = (span, inner_spans);
+            Ok(())
+        }
+        Some(_) => Err(AonError::KeyCollision(path[..=depth].join("."), span)),
+        None => {
+            let mut inner = vec![];
+            let mut inner_spans = KeySpans::default();
+            insert_field(&mut inner, &mut inner_spans, (path, span), depth + 1, value, value_spans, policy)?;
+            fields.push((key.clone(), BorrowedValue::Struct(inner)));
+            spans.push(span, inner_spans, policy);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{representation::{UnionPayload, value::Number}, deserializer::{deserialize, deserialize_borrowed, deserialize_json, deserialize_partial, deserialize_reader, lexer::Lexer, span::{position_at, span, Trickle}}, serializer::ToAon};
+    use super::*;
+    use std::io;
+
+    fn word(string: &str) -> TokenKind<'_> {
+        TokenKind::Word(string.into())
+    }
+
+    fn parse(kinds: &[TokenKind]) -> Result<Value> {
+        let tokens = kinds.iter()
+            .map(|kind| Token::new(kind.clone(), Span::EMPTY))
+            .collect::<Vec<Token>>();
+
+        parse_tokens(&tokens)
+    }
+
+    #[test]
+    fn test_primitive() {
+        let value = parse(&[TokenKind::Number(Number::PosInt(1)), TokenKind::EOF]);
+
+        assert_eq!(value, Ok(Value::Number(Number::PosInt(1))));
+    }
+
+    #[test]
+    fn test_struct() {
+        let tokens = [
+            TokenKind::LeftBrace,
+            word("name"), TokenKind::Colon, word("John Doe"), TokenKind::Comma,
+            word("age"), TokenKind::Colon, TokenKind::Number(Number::PosInt(42)), TokenKind::Comma,
+            TokenKind::RightBrace,
+            TokenKind::EOF,
+        ];
+
+        assert_eq!(parse(&tokens), Ok(Value::Struct(vec![
+            ("name".to_owned(), Value::String("John Doe".to_owned())),
+            ("age".to_owned(), Value::Number(Number::PosInt(42))),
+        ])));
+    }
+
+    #[test]
+    fn test_nested() {
+        let tokens = [
+            TokenKind::LeftBracket,
+            TokenKind::Comment(" first".into()),
+            TokenKind::Hash, word("some"), TokenKind::LeftBrace,
+            word("value"), TokenKind::Colon, TokenKind::LeftBracket, TokenKind::RightBracket,
+            TokenKind::RightBrace, TokenKind::Comma,
+            TokenKind::LeftBrace, TokenKind::RightBrace,
+            TokenKind::RightBracket,
+            TokenKind::EOF,
+        ];
+
+        assert_eq!(parse(&tokens), Ok(Value::Array(vec![
+            Value::Union("some".to_owned(), UnionPayload::Struct(vec![("value".to_owned(), Value::Array(vec![]))])),
+            Value::Struct(vec![]),
+        ])));
+    }
+
+    #[test]
+    fn test_dot_notation() {
+        let tokens = [
+            TokenKind::LeftBrace,
+            word("a"), TokenKind::Dot, word("b"), TokenKind::Dot, word("c"), TokenKind::Colon, TokenKind::Null, TokenKind::Comma,
+            word("a"), TokenKind::Dot, word("d"), TokenKind::Colon, TokenKind::Bool(true), TokenKind::Comma,
+            word("e"), TokenKind::Colon, TokenKind::LeftBrace, TokenKind::RightBrace, TokenKind::Comma,
+            word("e"), TokenKind::Dot, word("f"), TokenKind::Colon, TokenKind::Bool(false),
+            TokenKind::RightBrace,
+            TokenKind::EOF,
+        ];
+
+        assert_eq!(parse(&tokens), Ok(Value::Struct(vec![
+            ("a".to_owned(), Value::Struct(vec![
+                ("b".to_owned(), Value::Struct(vec![("c".to_owned(), Value::Null)])),
+                ("d".to_owned(), Value::Bool(true)),
+            ])),
+            ("e".to_owned(), Value::Struct(vec![("f".to_owned(), Value::Bool(false))])),
+        ])));
+    }
+
+    #[test]
+    fn test_dot_notation_errors() {
+        let collision = [
+            TokenKind::LeftBrace,
+            word("a"), TokenKind::Dot, word("b"), TokenKind::Colon, TokenKind::Null, TokenKind::Comma,
+            word("a"), TokenKind::Dot, word("b"), TokenKind::Dot, word("c"), TokenKind::Colon, TokenKind::Null,
+            TokenKind::RightBrace,
+            TokenKind::EOF,
+        ];
+        assert_eq!(parse(&collision), Err(AonError::KeyCollision("a.b".to_owned(), Span::EMPTY)));
+
+        // A quoted key is one segment, dots and all.
+        let dotted_word = [TokenKind::LeftBrace, word("a.b"), TokenKind::Colon, TokenKind::Null, TokenKind::RightBrace, TokenKind::EOF];
+        assert_eq!(parse(&dotted_word), Ok(Value::Struct(vec![("a.b".to_owned(), Value::Null)])));
+    }
+
+    #[test]
+    fn test_errors() {
+        let missing_colon = [TokenKind::LeftBrace, word("key"), word("value"), TokenKind::RightBrace, TokenKind::EOF];
+        assert_eq!(parse(&missing_colon), Err(AonError::UnexpectedToken(word("value"), Span::EMPTY)));
+
+        let unclosed = [TokenKind::LeftBracket, TokenKind::Null, TokenKind::Comma, TokenKind::EOF];
+        assert_eq!(parse(&unclosed), Err(AonError::UnexpectedEndOfFile(Span::EMPTY)));
+
+        let leading_comma = [TokenKind::LeftBracket, TokenKind::Comma, TokenKind::RightBracket, TokenKind::EOF];
+        assert_eq!(parse(&leading_comma), Err(AonError::UnexpectedToken(TokenKind::Comma, Span::EMPTY)));
+
+        let trailing = [TokenKind::Null, TokenKind::Null, TokenKind::EOF];
+        assert_eq!(parse(&trailing), Err(AonError::UnexpectedToken(TokenKind::Null, Span::EMPTY)));
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let aon = r#"{ name: "server", "quoted key": 1, nested.key-name: #some { null: true }, odd: #"with space" {} }"#;
+
+        assert_eq!(deserialize(aon), Ok(Value::Struct(vec![
+            ("name".to_owned(), Value::String("server".to_owned())),
+            ("quoted key".to_owned(), Value::Number(Number::PosInt(1))),
+            ("nested".to_owned(), Value::Struct(vec![
+                ("key-name".to_owned(), Value::Union("some".to_owned(), UnionPayload::Struct(vec![("null".to_owned(), Value::Bool(true))]))),
+            ])),
+            ("odd".to_owned(), Value::Union("with space".to_owned(), UnionPayload::Struct(vec![]))),
+        ])));
+
+        let span = span(7, 10);
+        assert_eq!(deserialize("{ key: nul }"), Err(AonError::InvalidKeyword("nul".to_owned(), span)));
+    }
+
+    #[test]
+    fn test_unit_union() {
+        let aon = "{ a: #none, b: #none {}, c: [#less, #greater], d: #none }";
+        let value = deserialize(aon);
+
+        assert_eq!(value, Ok(Value::Struct(vec![
+            ("a".to_owned(), Value::Union("none".to_owned(), UnionPayload::Unit)),
+            ("b".to_owned(), Value::Union("none".to_owned(), UnionPayload::Struct(vec![]))),
+            ("c".to_owned(), Value::Array(vec![
+                Value::Union("less".to_owned(), UnionPayload::Unit),
+                Value::Union("greater".to_owned(), UnionPayload::Unit),
+            ])),
+            ("d".to_owned(), Value::Union("none".to_owned(), UnionPayload::Unit)),
+        ])));
+
+        let formatter = crate::serializer::formatter::Formatter::default();
+        assert_eq!(value.unwrap().to_aon(&formatter), "{a:#none,b:#none{},c:[#less,#greater],d:#none}");
+    }
+
+    #[test]
+    fn test_tuple_union() {
+        let aon = "[#some(42), #point(1, -2,), #wrap(#some({ a: 1 })), #empty()]";
+        let value = deserialize(aon);
+
+        assert_eq!(value, Ok(Value::Array(vec![
+            Value::Union("some".to_owned(), UnionPayload::Tuple(vec![Value::Number(Number::PosInt(42))])),
+            Value::Union("point".to_owned(), UnionPayload::Tuple(vec![
+                Value::Number(Number::PosInt(1)),
+                Value::Number(Number::NegInt(-2)),
+            ])),
+            Value::Union("wrap".to_owned(), UnionPayload::Tuple(vec![
+                Value::Union("some".to_owned(), UnionPayload::Tuple(vec![
+                    Value::Struct(vec![("a".to_owned(), Value::Number(Number::PosInt(1)))]),
+                ])),
+            ])),
+            Value::Union("empty".to_owned(), UnionPayload::Tuple(vec![])),
+        ])));
+
+        let formatter = crate::serializer::formatter::Formatter::default();
+        assert_eq!(value.unwrap().to_aon(&formatter), "[#some(42),#point(1,-2),#wrap(#some({a:1})),#empty()]");
+
+        let span = span(8, 9);
+        assert_eq!(deserialize("#some(1 2)"), Err(AonError::UnexpectedToken(TokenKind::Number(Number::PosInt(2)), span)));
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let aon = r#"{ plain: "text", "esc\tkey": "a\"b", true: #some(["x"]) }"#;
+        let value = deserialize_borrowed(aon).unwrap();
+
+        let BorrowedValue::Struct(fields) = &value else { panic!("expected a struct") };
+        assert!(matches!(&fields[0], (Cow::Borrowed("plain"), BorrowedValue::String(Cow::Borrowed("text")))));
+        assert!(matches!(&fields[1], (Cow::Owned(key), BorrowedValue::String(Cow::Owned(string))) if key == "esc\tkey" && string == "a\"b"));
+        assert!(matches!(&fields[2], (Cow::Borrowed("true"), BorrowedValue::Union(Cow::Borrowed("some"), _))));
+
+        assert_eq!(value.into_owned(), deserialize(aon).unwrap());
+
+        // The same goes for single quoted strings.
+        let lenient = ParseOptions::new().dialect(Dialect::Lenient);
+        let aon = r#"{ plain: "text", "esc\tkey": 'a\'b', true: #some(["x"]) }"#;
+        let value = parse_with(Lexer::new(aon).with_options(&lenient), &lenient).unwrap();
+
+        let BorrowedValue::Struct(fields) = &value else { panic!("expected a struct") };
+        assert!(matches!(&fields[1], (Cow::Owned(key), BorrowedValue::String(Cow::Owned(string))) if key == "esc\tkey" && string == "a'b"));
+    }
+
+    #[test]
+    fn test_stream() {
+        // Tokens are only lexed as the parser gets to them, so an error stops
+        // it before the rest of the input is looked at.
+        let error = deserialize("[1 2 $]");
+        assert_eq!(error, Err(AonError::UnexpectedToken(TokenKind::Number(Number::PosInt(2)), span(3, 4))));
+
+        let tokens = [Ok(Token::new(TokenKind::LeftBracket, Span::EMPTY)), Err(AonError::Custom("failed".to_owned()))];
+        assert_eq!(super::parse(tokens.into_iter()), Err(AonError::Custom("failed".to_owned())));
+        assert_eq!(parse(&[TokenKind::Null]), Ok(Value::Null));
+    }
+
+    #[test]
+    fn test_reader() {
+        let aon = "{ name: \"café ☕\", tags: [\"a\\n\", #some(1)], // 😀\n nested.key: -1.5 }";
+        for size in [1, 2, 3, 7, 64] {
+            assert_eq!(deserialize_reader(Trickle(aon.as_bytes(), size, false)), deserialize(aon));
+        }
+
+        let error = deserialize_reader(Trickle(b"[1, 2", 2, true));
+        assert_eq!(error, Err(AonError::Io(io::ErrorKind::ConnectionReset, "reset".to_owned())));
+        assert_eq!(error.unwrap_err().to_string(), "i/o error: reset");
+
+        let error = deserialize_reader(Trickle(b"[\"a\xFF\"]", 64, false));
+        assert!(matches!(error, Err(AonError::Io(io::ErrorKind::InvalidData, _))));
+        let error = deserialize_reader(Trickle(b"\"\xE2\x82", 64, false));
+        assert!(matches!(error, Err(AonError::Io(io::ErrorKind::UnexpectedEof, _))));
+
+        let error = deserialize_reader(Trickle(b"[1 2]", 1, false));
+        assert!(matches!(error, Err(AonError::UnexpectedToken(TokenKind::Number(Number::PosInt(2)), _))));
+        assert_eq!(crate::from_reader::<Vec<u8>>(Trickle(b"[1, 2]", 1, false)), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_recovery() {
+        let codes = |errors: &AonErrors| errors.iter().map(AonError::code).collect::<Vec<_>>();
+
+        let (value, errors) = deserialize_partial("{ a: 1, b: , c: 3, d: [1 2, , 4] }");
+        assert_eq!(value, deserialize("{ a: 1, b: null, c: 3, d: [1, null, 4] }").unwrap());
+        assert_eq!(errors.to_string(), "1:12: unexpected token ','\n1:26: unexpected token '2'\n1:29: unexpected token ','");
+
+        // The nulls left in place of values are told apart from written ones
+        // by their spans.
+        let offsets = |errors: &AonErrors| errors.placeholders().iter().map(|span| span.start.offset).collect::<Vec<_>>();
+        assert_eq!(offsets(&errors), [8, 28]);
+        assert_eq!(offsets(&deserialize_partial("{ a: null, b: [null, ] }").1), Vec::<usize>::new());
+        assert_eq!(offsets(&deserialize_partial("$").1), [1]);
+
+        // Errors from the lexer do not leave the tokens after them reported
+        // as out of place.
+        let (value, errors) = deserialize_partial(r#"{ a: $, b: "x\q", c: nul, d: [1, }, e: 5 }"#);
+        assert_eq!(value, deserialize("{ a: null, b: null, c: null, d: [1, null] }").unwrap());
+        assert_eq!(codes(&errors), ["E0001", "E0008", "E0009", "E0004", "E0004"]);
+
+        let (value, errors) = deserialize_partial("{ a: 1, a.b: 2, \"c.d\": 3, e 4, f: #(1), g: #some(1 2) }");
+        assert_eq!(value, deserialize("{ a: 1, \"c.d\": 3, e: null, f: null, g: #some(1) }").unwrap());
+        assert_eq!(codes(&errors), ["E0006", "E0004", "E0004", "E0004"]);
+
+        // A missing delimiter is only reported once, not by every container
+        // left open.
+        let (value, errors) = deserialize_partial("{ a: { b: [1, #x(2");
+        assert_eq!(value, deserialize("{ a.b: [1, #x(2)] }").unwrap());
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors.iter().next(), Some(AonError::UnexpectedEndOfFile(_))));
+
+        assert_eq!(deserialize_partial("1 2 3"), (Value::Number(Number::PosInt(1)), AonErrors::new(vec![deserialize("1 2 3").unwrap_err()])));
+        assert_eq!(codes(&deserialize_partial("$").1), ["E0001"]);
+        assert_eq!(deserialize_partial("{ a: [1, #b] }"), (deserialize("{ a: [1, #b] }").unwrap(), AonErrors::default()));
+    }
+
+    #[test]
+    fn test_spans() {
+        let error = deserialize("{\n    \"a\": 1,\n    \"b\" 2\n}");
+        let span = Span::new(position_at(22, 3, 9), position_at(23, 3, 10));
+        assert_eq!(error, Err(AonError::UnexpectedToken(TokenKind::Number(Number::PosInt(2)), span)));
+        assert_eq!(error.unwrap_err().to_string(), "3:9: unexpected token '2'");
+
+        let error = deserialize("{ \"a\".\"b\": 1, \"a\".\"b\".\"c\": 2 }");
+        let span = Span::new(position_at(14, 1, 15), position_at(25, 1, 26));
+        assert_eq!(error, Err(AonError::KeyCollision("a.b".to_owned(), span)));
+
+        let error = deserialize("[1, ");
+        let span = Span::new(position_at(4, 1, 5), position_at(4, 1, 5));
+        assert_eq!(error, Err(AonError::UnexpectedEndOfFile(span)));
+
+        let error = deserialize("[\"é\", }");
+        let span = Span::new(position_at(7, 1, 7), position_at(8, 1, 8));
+        assert_eq!(error, Err(AonError::UnexpectedToken(TokenKind::RightBrace, span)));
+    }
+
+    #[test]
+    fn test_json() {
+        let json = r#"{ "name": "server", "port": 8080, "ratio": -0.5, "tags": ["a", "b"], "tls": true, "proxy": null }"#;
+        assert_eq!(deserialize_json(json), deserialize(json));
+
+        // Keys are taken as they are, dots and all.
+        let value = Value::Struct(vec![("a.b".to_owned(), Value::Number(Number::PosInt(1)))]);
+        assert_eq!(deserialize_json(r#"{ "a.b": 1 }"#), Ok(value));
+
+        let json = r#"[
+            { "type": "none" },
+            { "type": "some", "values": [42] },
+            { "type": "person", "fields": { "name": "John", "pet": { "type": "cat" } } },
+            { "fields": { "type": "a", "values": [1] }, "type": "b" },
+            { "type": "user", "name": "John" },
+            { "values": [1], "type": "pair", "other": 2 },
+            { "type": "pair", "fields": 2 },
+            { "type": 1 }
+        ]"#;
+
+        let aon = r#"[
+            #none, #some(42), #person{ name: "John", pet: #cat }, #b{ type: "a", values: [1] },
+            { type: "user", name: "John" }, { values: [1], type: "pair", other: 2 }, { type: "pair", fields: 2 }, { type: 1 }
+        ]"#;
+        assert_eq!(deserialize_json(json), deserialize(aon));
+    }
+
+    #[test]
+    fn test_json_errors() {
+        assert_eq!(deserialize_json("[1, 2,]"), Err(AonError::UnexpectedToken(TokenKind::RightBracket, span(6, 7))));
+        assert_eq!(deserialize_json(r#"{ "a": 1, }"#), Err(AonError::UnexpectedToken(TokenKind::RightBrace, span(10, 11))));
+        assert_eq!(deserialize_json("{ a: 1 }"), Err(AonError::UnexpectedToken(TokenKind::Ident("a".into()), span(2, 3))));
+        assert_eq!(deserialize_json("[fast]"), Err(AonError::InvalidKeyword("fast".to_owned(), span(1, 5))));
+        assert_eq!(deserialize_json("1 // one"), Err(AonError::UnexpectedCharacter('/', span(2, 3))));
+        assert_eq!(deserialize_json("#none"), Err(AonError::UnexpectedCharacter('#', span(0, 1))));
+        assert_eq!(deserialize_json(r#"{ "a".b: 1 }"#), Err(AonError::UnexpectedCharacter('.', span(5, 6))));
+    }
 }
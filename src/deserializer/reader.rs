@@ -0,0 +1,155 @@
+use std::{collections::VecDeque, io::{self, Read}, str};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+// Decodes the chars of a UTF-8 stream a chunk at a time. A char split across
+// two chunks is held back until the rest of it has been read.
+//
+// The stream ends at the first I/O error or invalid UTF-8, which is kept to
+// be reported in place of whatever the early end leads to.
+pub(crate) struct ReadChars<'a> {
+    reader: Box<dyn Read + 'a>,
+    // Bytes of an incomplete char at the end of the last chunk.
+    pending: Vec<u8>,
+    chars: VecDeque<char>,
+    error: Option<io::Error>,
+    done: bool,
+    // How many more bytes may be read, and whether the stream went on past
+    // that.
+    remaining: Option<usize>,
+    truncated: bool,
+}
+
+impl<'a> ReadChars<'a> {
+    pub(crate) fn new(reader: impl Read + 'a) -> ReadChars<'a> {
+        ReadChars {
+            reader: Box::new(reader),
+            pending: vec![],
+            chars: VecDeque::new(),
+            error: None,
+            done: false,
+            remaining: None,
+            truncated: false,
+        }
+    }
+
+    // Stops reading after `limit` more bytes.
+    pub(crate) fn limit(&mut self, limit: usize) {
+        self.remaining = Some(limit);
+    }
+
+    pub(crate) fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    // The char `index` chars ahead of the next one.
+    pub(crate) fn peek(&mut self, index: usize) -> Option<char> {
+        while self.chars.len() <= index && !self.done {
+            self.fill();
+        }
+
+        self.chars.get(index).copied()
+    }
+
+    pub(crate) fn next(&mut self) -> Option<char> {
+        self.peek(0)?;
+        self.chars.pop_front()
+    }
+
+    pub(crate) fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    fn fill(&mut self) {
+        let mut chunk = [0; CHUNK_SIZE];
+
+        // One byte more than the limit allows is read, to tell whether the
+        // stream goes on past it.
+        let size = self.remaining.map_or(CHUNK_SIZE, |remaining| CHUNK_SIZE.min(remaining + 1));
+
+        let mut read = match self.reader.read(&mut chunk[..size]) {
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => return,
+            Err(error) => return self.fail(error),
+        };
+
+        if let Some(remaining) = &mut self.remaining {
+            if read > *remaining {
+                read = *remaining;
+                self.truncated = true;
+                self.done = true;
+            }
+
+            *remaining -= read;
+        }
+
+        if read == 0 && self.truncated {
+            return;
+        }
+
+        if read == 0 {
+            if !self.pending.is_empty() {
+                return self.fail(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended within a UTF-8 sequence"));
+            }
+
+            self.done = true;
+            return;
+        }
+
+        self.pending.extend_from_slice(&chunk[..read]);
+
+        let valid = match str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(error) => {
+                self.decode(error.valid_up_to());
+                return self.fail(io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"));
+            }
+        };
+
+        self.decode(valid);
+    }
+
+    // Moves the first `valid` bytes of `pending`, which are known to be
+    // valid UTF-8, over to the decoded chars.
+    fn decode(&mut self, valid: usize) {
+        let text = str::from_utf8(&self.pending[..valid]).expect("bytes were validated");
+        self.chars.extend(text.chars());
+        self.pending.drain(..valid);
+    }
+
+    fn fail(&mut self, error: io::Error) {
+        self.error = Some(error);
+        self.done = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deserializer::span::Trickle;
+    use super::*;
+
+    fn read_all(mut chars: ReadChars) -> (String, Option<io::ErrorKind>) {
+        let string = std::iter::from_fn(|| chars.next()).collect();
+        (string, chars.take_error().map(|error| error.kind()))
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        let text = "aé€😀 z";
+
+        for size in 1..=text.len() {
+            assert_eq!(read_all(ReadChars::new(Trickle(text.as_bytes(), size, false))), (text.to_owned(), None));
+        }
+
+        let mut chars = ReadChars::new(Trickle(text.as_bytes(), 1, false));
+        assert_eq!((chars.peek(0), chars.peek(1), chars.peek(3)), (Some('a'), Some('é'), Some('😀')));
+        assert_eq!(chars.next(), Some('a'));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(read_all(ReadChars::new(Trickle(b"ab\xFFcd", 1, false))), ("ab".to_owned(), Some(io::ErrorKind::InvalidData)));
+        assert_eq!(read_all(ReadChars::new(Trickle(b"ab\xE2\x82", 2, false))), ("ab".to_owned(), Some(io::ErrorKind::UnexpectedEof)));
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt::Display;
+
+// A location in the source text. `offset` is in bytes, `line` and `column`
+// are 1-based with columns counted in chars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub const START: Position = Position { offset: 0, line: 1, column: 1 };
+
+    pub fn advance(self, c: char) -> Position {
+        match c {
+            '\n' => Position { offset: self.offset + c.len_utf8(), line: self.line + 1, column: 1 },
+            _ => Position { offset: self.offset + c.len_utf8(), line: self.line, column: self.column + 1 },
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::START
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+// The half-open range `start..end` of source text a token or error covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub const EMPTY: Span = Span { start: Position::START, end: Position::START };
+
+    pub fn new(start: Position, end: Position) -> Span {
+        Span { start, end }
+    }
+
+    // A span covering the single char `c` starting at `start`.
+    pub fn char(start: Position, c: char) -> Span {
+        Span { start, end: start.advance(c) }
+    }
+
+    pub fn to(self, other: Span) -> Span {
+        Span { start: self.start, end: other.end }
+    }
+}
+
+// Positions and spans for tests. Those on the first line of ASCII text have
+// columns that follow from their offsets.
+#[cfg(test)]
+pub(crate) fn position(offset: usize) -> Position {
+    Position { offset, line: 1, column: offset + 1 }
+}
+
+#[cfg(test)]
+pub(crate) fn position_at(offset: usize, line: usize, column: usize) -> Position {
+    Position { offset, line, column }
+}
+
+#[cfg(test)]
+pub(crate) fn span(start: usize, end: usize) -> Span {
+    Span::new(position(start), position(end))
+}
+
+// A reader handing out its bytes a few at a time, to split chars and tokens
+// across reads. It fails once it runs dry if told to.
+#[cfg(test)]
+pub(crate) struct Trickle<'a>(pub(crate) &'a [u8], pub(crate) usize, pub(crate) bool);
+
+#[cfg(test)]
+impl std::io::Read for Trickle<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        if self.0.is_empty() && self.2 {
+            return Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"));
+        }
+
+        let read = self.0.len().min(self.1).min(buffer.len());
+        buffer[..read].copy_from_slice(&self.0[..read]);
+        self.0 = &self.0[read..];
+        Ok(read)
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
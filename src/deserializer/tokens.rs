@@ -1,46 +1,111 @@
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display};
 
 use crate::representation::value::Number;
 
+use super::span::Span;
+
 #[derive(Debug, PartialEq, Clone)]
-pub enum Token {
-    Comment(String),
+pub struct Token<'a> {
+    pub kind: TokenKind<'a>,
+    pub span: Span,
+}
+
+impl<'a> Token<'a> {
+    pub fn new(kind: TokenKind<'a>, span: Span) -> Token<'a> {
+        Token { kind, span }
+    }
+
+    pub fn into_owned(self) -> Token<'static> {
+        Token { kind: self.kind.into_owned(), span: self.span }
+    }
+}
+
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+// Text is borrowed from the input wherever it is written as is, only strings
+// with escapes in them are owned.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenKind<'a> {
+    Comment(Cow<'a, str>),
+    BlockComment(Cow<'a, str>),
     SingleQuote,
     DoubleQuote,
     Colon,
     Comma,
+    Dot,
     Hash,
     LeftBrace,
     RightBrace,
     LeftBracket,
     RightBracket,
+    LeftParen,
+    RightParen,
     Null,
     Bool(bool),
     Number(Number),
 
     // Can be the value of a string, but can also be a key in a struct.
-    Word(String),
+    Word(Cow<'a, str>),
+
+    // An unquoted key or union variant name.
+    Ident(Cow<'a, str>),
     EOF,
 }
 
-impl Display for Token {
+impl TokenKind<'_> {
+    pub fn into_owned(self) -> TokenKind<'static> {
+        match self {
+            TokenKind::Comment(comment) => TokenKind::Comment(Cow::Owned(comment.into_owned())),
+            TokenKind::BlockComment(comment) => TokenKind::BlockComment(Cow::Owned(comment.into_owned())),
+            TokenKind::SingleQuote => TokenKind::SingleQuote,
+            TokenKind::DoubleQuote => TokenKind::DoubleQuote,
+            TokenKind::Colon => TokenKind::Colon,
+            TokenKind::Comma => TokenKind::Comma,
+            TokenKind::Dot => TokenKind::Dot,
+            TokenKind::Hash => TokenKind::Hash,
+            TokenKind::LeftBrace => TokenKind::LeftBrace,
+            TokenKind::RightBrace => TokenKind::RightBrace,
+            TokenKind::LeftBracket => TokenKind::LeftBracket,
+            TokenKind::RightBracket => TokenKind::RightBracket,
+            TokenKind::LeftParen => TokenKind::LeftParen,
+            TokenKind::RightParen => TokenKind::RightParen,
+            TokenKind::Null => TokenKind::Null,
+            TokenKind::Bool(bool) => TokenKind::Bool(bool),
+            TokenKind::Number(number) => TokenKind::Number(number),
+            TokenKind::Word(string) => TokenKind::Word(Cow::Owned(string.into_owned())),
+            TokenKind::Ident(ident) => TokenKind::Ident(Cow::Owned(ident.into_owned())),
+            TokenKind::EOF => TokenKind::EOF,
+        }
+    }
+}
+
+impl Display for TokenKind<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Token::Comment(c) => write!(f, "//{}", c),
-            Token::SingleQuote => write!(f, "'"),
-            Token::DoubleQuote => write!(f, "\""),
-            Token::Colon => write!(f, ":"),
-            Token::Comma => write!(f, ","),
-            Token::Hash => write!(f, "#"),
-            Token::LeftBrace => write!(f, "{{"),
-            Token::RightBrace => write!(f, "}}"),
-            Token::LeftBracket => write!(f, "["),
-            Token::RightBracket => write!(f, "]"),
-            Token::Null => write!(f, "null"),
-            Token::Bool(bool) => write!(f, "{}", bool),
-            Token::Number(number) => write!(f, "{}", number),
-            Token::Word(string) => write!(f, "\"{}\"", string),
-            Token::EOF => write!(f, ""),
+            TokenKind::Comment(c) => write!(f, "//{}", c),
+            TokenKind::BlockComment(c) => write!(f, "/*{}*/", c),
+            TokenKind::SingleQuote => write!(f, "'"),
+            TokenKind::DoubleQuote => write!(f, "\""),
+            TokenKind::Colon => write!(f, ":"),
+            TokenKind::Comma => write!(f, ","),
+            TokenKind::Dot => write!(f, "."),
+            TokenKind::Hash => write!(f, "#"),
+            TokenKind::LeftBrace => write!(f, "{{"),
+            TokenKind::RightBrace => write!(f, "}}"),
+            TokenKind::LeftBracket => write!(f, "["),
+            TokenKind::RightBracket => write!(f, "]"),
+            TokenKind::LeftParen => write!(f, "("),
+            TokenKind::RightParen => write!(f, ")"),
+            TokenKind::Null => write!(f, "null"),
+            TokenKind::Bool(bool) => write!(f, "{}", bool),
+            TokenKind::Number(number) => write!(f, "{}", number),
+            TokenKind::Word(string) => write!(f, "\"{}\"", string),
+            TokenKind::Ident(ident) => write!(f, "{}", ident),
+            TokenKind::EOF => write!(f, ""),
         }
     }
 }
@@ -49,9 +114,14 @@ impl Display for Token {
 pub(crate) const SINGLE_QUOTE: char = '\'';
 pub(crate) const DOUBLE_QUOTE: char = '"';
 pub(crate) const SLASH: char = '/';
+pub(crate) const STAR: char = '*';
+pub(crate) const BACKSLASH: char = '\\';
 
 pub(crate) const COLON: char = ':';
 pub(crate) const COMMA: char = ',';
+pub(crate) const DOT: char = '.';
+pub(crate) const MINUS: char = '-';
+pub(crate) const PLUS: char = '+';
 pub(crate) const HASH: char = '#';
 
 pub(crate) const LEFT_BRACE: char = '{';
@@ -60,6 +130,35 @@ pub(crate) const RIGHT_BRACE: char = '}';
 pub(crate) const LEFT_BRACKET: char = '[';
 pub(crate) const RIGHT_BRACKET: char = ']';
 
+pub(crate) const LEFT_PAREN: char = '(';
+pub(crate) const RIGHT_PAREN: char = ')';
+
+// The keys of a union written as a JSON object.
+pub(crate) const UNION_TYPE: &str = "type";
+pub(crate) const UNION_VALUES: &str = "values";
+pub(crate) const UNION_FIELDS: &str = "fields";
+
 pub(crate) const NULL: &str = "null";
 pub(crate) const TRUE: &str = "true";
-pub(crate) const FALSE: &str = "false";
\ No newline at end of file
+pub(crate) const FALSE: &str = "false";
+
+// A line of its own between the records of a multi-document stream.
+pub(crate) const DOCUMENT_SEPARATOR: &str = "---";
+
+pub(crate) fn is_word_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+pub(crate) fn is_word_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == MINUS
+}
+
+// Identifiers start with a letter or `_` followed by any number of letters,
+// digits, `_` and `-`. Keywords are not identifiers.
+pub(crate) fn is_identifier(string: &str) -> bool {
+    let mut chars = string.chars();
+
+    chars.next().is_some_and(is_word_start)
+        && chars.all(is_word_continue)
+        && ![NULL, TRUE, FALSE].contains(&string)
+}
@@ -0,0 +1,372 @@
+use serde::{
+    de::{self, value::{MapDeserializer, SeqDeserializer, StringDeserializer}, IntoDeserializer, Visitor},
+    forward_to_deserialize_any,
+};
+
+use crate::{
+    representation::{Value, UnionPayload, value::Number},
+    serializer::value_serializer::{NONE, SOME},
+    deserializer::tokens,
+    error::{AonError, Result},
+};
+
+// Deserializes a parsed `Value` tree into any `Deserialize` type.
+//
+// Unions map onto externally tagged enums, mirroring `ValueSerializer`:
+// `#variant` is a unit variant, `#variant(..)` a newtype variant,
+// `#variant(.., ..)` a tuple variant and `#variant { .. }` a struct variant.
+// A union read into a struct or map is read as the JSON object it stands
+// for, so objects that `deserialize_json` took for unions still fit types
+// with a `type` field.
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = AonError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(bool) => visitor.visit_bool(bool),
+            Value::Number(Number::PosInt(value)) => visitor.visit_u64(value),
+            Value::Number(Number::BigPosInt(value)) => visitor.visit_u128(value),
+            Value::Number(Number::NegInt(value)) => visitor.visit_i64(value),
+            Value::Number(Number::BigNegInt(value)) => visitor.visit_i128(value),
+            Value::Number(Number::Float(value)) => visitor.visit_f64(value),
+            Value::String(string) => visitor.visit_string(string),
+            Value::Struct(fields) => visitor.visit_map(MapDeserializer::new(fields.into_iter())),
+            Value::Union(variant, payload) => visitor.visit_enum(UnionDeserializer { variant, payload }),
+            Value::Array(values) => visitor.visit_seq(SeqDeserializer::new(values.into_iter())),
+        }
+    }
+
+    // Options are read from `#none` and `#some(value)`. `null` and bare
+    // values are accepted as well, as hand written documents tend to use them.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            Value::Union(variant, UnionPayload::Unit) if variant == NONE => visitor.visit_none(),
+            Value::Union(variant, UnionPayload::Tuple(mut values)) if variant == SOME && values.len() == 1 => {
+                visitor.visit_some(values.remove(0))
+            }
+            other => visitor.visit_some(other),
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Union(variant, payload) => visitor.visit_map(MapDeserializer::new(union_fields(variant, payload).into_iter())),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Value::Union(variant, payload) => visitor.visit_enum(UnionDeserializer { variant, payload }),
+            Value::String(variant) => visitor.visit_enum(UnionDeserializer { variant, payload: UnionPayload::Unit }),
+            other => Err(de::Error::invalid_type(unexpected(&other), &"a union")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct identifier
+        ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, AonError> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+struct UnionDeserializer {
+    variant: String,
+    payload: UnionPayload,
+}
+
+impl<'de> de::EnumAccess<'de> for UnionDeserializer {
+    type Error = AonError;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, VariantDeserializer)> {
+        let variant: StringDeserializer<AonError> = self.variant.into_deserializer();
+        let value = seed.deserialize(variant)?;
+
+        Ok((value, VariantDeserializer { payload: self.payload }))
+    }
+}
+
+struct VariantDeserializer {
+    payload: UnionPayload,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = AonError;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.payload {
+            UnionPayload::Unit => Ok(()),
+            UnionPayload::Struct(fields) if fields.is_empty() => Ok(()),
+            UnionPayload::Tuple(values) if values.is_empty() => Ok(()),
+            other => Err(de::Error::invalid_type(unexpected_payload(&other), &"a unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        // Named fields are taken to be the fields of a struct payload.
+        match self.payload {
+            UnionPayload::Tuple(mut values) if values.len() == 1 => seed.deserialize(values.remove(0)),
+            UnionPayload::Struct(fields) => seed.deserialize(Value::Struct(fields)),
+            other => Err(de::Error::invalid_type(unexpected_payload(&other), &"a newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        match self.payload {
+            UnionPayload::Tuple(values) => visitor.visit_seq(SeqDeserializer::new(values.into_iter())),
+            other => Err(de::Error::invalid_type(unexpected_payload(&other), &visitor)),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        match self.payload {
+            UnionPayload::Struct(fields) => visitor.visit_map(MapDeserializer::new(fields.into_iter())),
+            UnionPayload::Unit => visitor.visit_map(MapDeserializer::new(std::iter::empty::<(String, Value)>())),
+            other => Err(de::Error::invalid_type(unexpected_payload(&other), &visitor)),
+        }
+    }
+}
+
+impl de::Error for AonError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        AonError::Custom(message.to_string())
+    }
+}
+
+// The fields of the JSON object a union is written as, the variant under
+// `type` followed by the payload under `values` or `fields`.
+fn union_fields(variant: String, payload: UnionPayload) -> Vec<(String, Value)> {
+    let mut fields = vec![(tokens::UNION_TYPE.to_owned(), Value::String(variant))];
+
+    match payload {
+        UnionPayload::Unit => {}
+        UnionPayload::Struct(payload) => fields.push((tokens::UNION_FIELDS.to_owned(), Value::Struct(payload))),
+        UnionPayload::Tuple(values) => fields.push((tokens::UNION_VALUES.to_owned(), Value::Array(values))),
+    }
+
+    fields
+}
+
+fn unexpected_payload(payload: &UnionPayload) -> de::Unexpected<'static> {
+    match payload {
+        UnionPayload::Unit => de::Unexpected::UnitVariant,
+        UnionPayload::Struct(_) => de::Unexpected::StructVariant,
+        UnionPayload::Tuple(values) if values.len() == 1 => de::Unexpected::NewtypeVariant,
+        UnionPayload::Tuple(_) => de::Unexpected::TupleVariant,
+    }
+}
+
+fn unexpected(value: &Value) -> de::Unexpected<'_> {
+    match value {
+        Value::Null => de::Unexpected::Unit,
+        Value::Bool(bool) => de::Unexpected::Bool(*bool),
+        Value::Number(Number::PosInt(value)) => de::Unexpected::Unsigned(*value),
+        Value::Number(Number::NegInt(value)) => de::Unexpected::Signed(*value),
+        Value::Number(Number::Float(value)) => de::Unexpected::Float(*value),
+        Value::Number(_) => de::Unexpected::Other("big integer"),
+        Value::String(string) => de::Unexpected::Str(string),
+        Value::Struct(_) => de::Unexpected::Map,
+        Value::Union(..) => de::Unexpected::Enum,
+        Value::Array(_) => de::Unexpected::Seq,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use std::collections::BTreeMap;
+
+    use crate::{deserializer::{from_str, from_value, deserialize_json}, serializer::to_string};
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        name: String,
+        port: u16,
+        ratio: f32,
+        tags: Vec<String>,
+        mode: Mode,
+        fallback: Mode,
+        retry: Option<Retry>,
+        timeout: Option<u32>,
+        shape: Shape,
+        point: Shape,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Retry {
+        attempts: u8,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Mode {
+        Fast,
+        Custom { level: u8 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Circle(u8),
+        Point(u8, u8),
+    }
+
+    #[test]
+    fn test_from_str() {
+        let aon = r#"
+        {
+            name: "server",
+            port: 8080,
+            ratio: 0.5,
+            tags: ["a", "b",],
+            mode: #Fast,
+            fallback: #Custom { level: 3 },
+            retry: #none,
+            timeout: #some(30),
+            shape: #Circle(4),
+            point: #Point(1, 2),
+        }
+        "#;
+
+        assert_eq!(from_str::<Config>(aon), Ok(Config {
+            name: "server".to_owned(),
+            port: 8080,
+            ratio: 0.5,
+            tags: vec!["a".to_owned(), "b".to_owned()],
+            mode: Mode::Fast,
+            fallback: Mode::Custom { level: 3 },
+            retry: None,
+            timeout: Some(30),
+            shape: Shape::Circle(4),
+            point: Shape::Point(1, 2),
+        }));
+
+        assert_eq!(from_str::<Option<Retry>>("{ attempts: 3 }"), Ok(Some(Retry { attempts: 3 })));
+        assert_eq!(from_str::<Option<Retry>>("null"), Ok(None));
+        assert_eq!(from_str::<Mode>(r#""Fast""#), Ok(Mode::Fast));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let values: Vec<Option<u32>> = vec![Some(42), None];
+        assert_eq!(to_string(&values).as_deref(), Ok("[#some(42),#none]"));
+        assert_eq!(from_str::<Vec<Option<u32>>>("[#some(42),#none]"), Ok(values));
+
+        let nested: Option<Option<u32>> = Some(None);
+        assert_eq!(to_string(&nested).as_deref(), Ok("#some(#none)"));
+        assert_eq!(from_str::<Option<Option<u32>>>("#some(#none)"), Ok(nested));
+
+        let results: Vec<std::result::Result<Shape, String>> = vec![Ok(Shape::Point(1, 2)), Err("failed".to_owned())];
+        assert_eq!(to_string(&results).as_deref(), Ok(r#"[#Ok(#Point(1,2)),#Err("failed")]"#));
+        assert_eq!(from_str::<Vec<std::result::Result<Shape, String>>>(r#"[#Ok(#Point(1,2)),#Err("failed")]"#), Ok(results));
+
+        // Keys with dots in them are quoted, which keeps them whole.
+        let hosts = BTreeMap::from([("example.com".to_owned(), 1)]);
+        assert_eq!(to_string(&hosts).as_deref(), Ok(r#"{"example.com":1}"#));
+        assert_eq!(from_str::<BTreeMap<String, u8>>(r#"{"example.com":1}"#), Ok(hosts));
+        assert_eq!(from_str::<BTreeMap<String, BTreeMap<String, ()>>>(r#"{a."b.c":null}"#), Ok(BTreeMap::from([
+            ("a".to_owned(), BTreeMap::from([("b.c".to_owned(), ())])),
+        ])));
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Tagged {
+        #[serde(rename = "type")]
+        kind: String,
+        fields: BTreeMap<String, String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User {
+        #[serde(rename = "type")]
+        kind: String,
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Values {
+        values: Vec<u8>,
+    }
+
+    #[test]
+    fn test_json_objects() {
+        let value = deserialize_json(r#"{ "type": "admin", "name": "x" }"#).unwrap();
+        assert_eq!(from_value::<User>(value), Ok(User { kind: "admin".to_owned(), name: "x".to_owned() }));
+
+        let value = deserialize_json(r#"{ "type": "admin", "fields": { "name": "x" } }"#).unwrap();
+        assert_eq!(value, Value::Union("admin".to_owned(), UnionPayload::Struct(vec![("name".to_owned(), Value::String("x".to_owned()))])));
+        assert_eq!(from_value::<BTreeMap<String, BTreeMap<String, String>>>(value.clone()).map(|map| map.len()), Err(AonError::Custom(
+            "invalid type: string \"admin\", expected a map".to_owned(),
+        )));
+        assert_eq!(from_value::<Tagged>(value), Ok(Tagged {
+            kind: "admin".to_owned(),
+            fields: BTreeMap::from([("name".to_owned(), "x".to_owned())]),
+        }));
+
+        let value = deserialize_json(r#"{ "type": "Point", "values": [1, 2] }"#).unwrap();
+        assert_eq!(from_value::<Shape>(value.clone()), Ok(Shape::Point(1, 2)));
+        assert_eq!(from_value::<Values>(value), Ok(Values { values: vec![1, 2] }));
+
+        let value = deserialize_json(r#"{ "type": "guest" }"#).unwrap();
+        assert_eq!(from_value::<BTreeMap<String, String>>(value), Ok(BTreeMap::from([("type".to_owned(), "guest".to_owned())])));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            from_str::<Retry>("{ attempts: 300 }"),
+            Err(AonError::Custom("invalid value: integer `300`, expected u8".to_owned())),
+        );
+
+        assert_eq!(
+            from_str::<Mode>("#Fast { level: 3 }"),
+            Err(AonError::Custom("invalid type: struct variant, expected a unit variant".to_owned())),
+        );
+
+        assert_eq!(
+            from_str::<Shape>("#Circle"),
+            Err(AonError::Custom("invalid type: unit variant, expected a newtype variant".to_owned())),
+        );
+
+        assert_eq!(
+            from_str::<Shape>("#Point(1)"),
+            Err(AonError::Custom("invalid length 1, expected tuple variant Shape::Point with 2 elements".to_owned())),
+        );
+
+        assert_eq!(
+            from_str::<Mode>("1"),
+            Err(AonError::Custom("invalid type: integer `1`, expected a union".to_owned())),
+        );
+
+        assert!(from_str::<Retry>("{").is_err());
+    }
+}
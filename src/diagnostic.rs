@@ -0,0 +1,178 @@
+use crate::{deserializer::tokens::{self, TokenKind}, error::{AonError, AonErrors}};
+
+impl AonError {
+    // A stable identifier for the kind of error, e.g. for looking it up in
+    // documentation or filtering logs.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AonError::UnexpectedCharacter(..) => "E0001",
+            AonError::InvalidNumber(..) => "E0002",
+            AonError::UnexpectedEndOfFile(..) => "E0003",
+            AonError::UnexpectedToken(..) => "E0004",
+            AonError::InvalidKey(..) => "E0005",
+            AonError::KeyCollision(..) => "E0006",
+            AonError::Custom(..) => "E0007",
+            AonError::InvalidEscape(..) => "E0008",
+            AonError::InvalidKeyword(..) => "E0009",
+            AonError::Io(..) => "E0010",
+            AonError::DepthLimitExceeded(..) => "E0011",
+            AonError::SizeLimitExceeded(..) => "E0012",
+            AonError::StringLimitExceeded(..) => "E0013",
+            AonError::ElementLimitExceeded(..) => "E0014",
+            AonError::DuplicateKey(..) => "E0015",
+            AonError::InvalidPath(..) => "E0016",
+            AonError::PathNotFound(..) => "E0017",
+            AonError::PathExists(..) => "E0018",
+            AonError::IndexOutOfBounds(..) => "E0019",
+            AonError::NotAUnion(..) => "E0020",
+        }
+    }
+
+    // A suggestion on how to fix the error, where there is a likely one.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            AonError::UnexpectedCharacter(tokens::SINGLE_QUOTE, _) =>
+                Some("strings are enclosed in double quotation marks, single ones need the lenient dialect"),
+            AonError::InvalidNumber(..) =>
+                Some("numbers are digits with an optional '-' sign and decimal part, e.g. -1.5"),
+            AonError::UnexpectedEndOfFile(..) =>
+                Some("a struct, union or array may be missing its closing delimiter"),
+            AonError::UnexpectedToken(TokenKind::Comma, _) =>
+                Some("commas may only separate values, not lead or repeat"),
+            AonError::UnexpectedToken(TokenKind::Comment(_) | TokenKind::BlockComment(_), _) =>
+                Some("comments may not be written inside a dotted key or between '#' and a variant"),
+            AonError::UnexpectedToken(TokenKind::Colon, _) =>
+                Some("colons may only follow a key inside a struct or union"),
+            AonError::KeyCollision(..) =>
+                Some("a dotted key can only add to a struct, not to a value defined earlier"),
+            AonError::DuplicateKey(..) =>
+                Some("remove one of the two, or write the fields of both in one struct"),
+            AonError::InvalidEscape(..) =>
+                Some(r#"valid escapes are \" \' \\ \/ \n \t \r \b \f \0, \u{XXXX} and \uXXXX"#),
+            AonError::InvalidKeyword(..) =>
+                Some("the only keywords are null, true and false, strings must be enclosed in quotation marks"),
+            _ => None,
+        }
+    }
+
+    // Renders the error along with the line of `source` it points at, the
+    // offending span underlined with carets:
+    //
+    //     error[E0004]: unexpected token '2'
+    //      --> 3:9
+    //       |
+    //     3 |     "b" 2
+    //       |         ^
+    //       = hint: ...
+    pub fn render(&self, source: &str) -> String {
+        let mut result = format!("error[{}]: {}\n", self.code(), self.message());
+
+        if let Some(span) = self.span() {
+            let line = source.lines().nth(span.start.line - 1).unwrap_or_default();
+            let number = span.start.line.to_string();
+            let gutter = " ".repeat(number.len());
+
+            // Keep tabs in the indentation so the carets line up with the
+            // source however wide the terminal renders them.
+            let indent = line.chars()
+                .take(span.start.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+
+            // Spans reaching past the line are cut off at its end.
+            let width = match span.end.line == span.start.line {
+                true => span.end.column.saturating_sub(span.start.column),
+                false => line.chars().count().saturating_sub(span.start.column - 1),
+            };
+
+            result.push_str(&format!("{} --> {}\n", gutter, span.start));
+            result.push_str(&format!("{} |\n", gutter));
+            result.push_str(&format!("{} | {}\n", number, line));
+            result.push_str(&format!("{} | {}{}\n", gutter, indent, "^".repeat(width.max(1))));
+        }
+
+        if let Some(hint) = self.hint() {
+            let gutter = " ".repeat(self.span().map_or(0, |span| span.start.line.to_string().len()));
+            result.push_str(&format!("{} = hint: {}\n", gutter, hint));
+        }
+
+        result
+    }
+}
+
+impl AonErrors {
+    // Renders every error as `AonError::render` does, one after another.
+    pub fn render(&self, source: &str) -> String {
+        self.iter()
+            .map(|error| error.render(source))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deserializer::{deserialize, deserialize_partial};
+
+    #[test]
+    fn test_render() {
+        let source = "{\n    \"a\": 1,\n    \"b\" 2\n}";
+        let error = deserialize(source).unwrap_err();
+
+        assert_eq!(error.render(source), concat!(
+            "error[E0004]: unexpected token '2'\n",
+            "  --> 3:9\n",
+            "  |\n",
+            "3 |     \"b\" 2\n",
+            "  |         ^\n",
+        ));
+    }
+
+    #[test]
+    fn test_render_hint() {
+        let source = "{\n\ta: 1,\n\ta.b: 2\n}";
+        let error = deserialize(source).unwrap_err();
+
+        assert_eq!(error.render(source), concat!(
+            "error[E0006]: key 'a' collides with a value that is not a struct\n",
+            "  --> 3:2\n",
+            "  |\n",
+            "3 | \ta.b: 2\n",
+            "  | \t^^^\n",
+            "  = hint: a dotted key can only add to a struct, not to a value defined earlier\n",
+        ));
+
+        let source = "[1, 2";
+        let error = deserialize(source).unwrap_err();
+
+        assert_eq!(error.render(source), concat!(
+            "error[E0003]: unexpected end of file\n",
+            "  --> 1:6\n",
+            "  |\n",
+            "1 | [1, 2\n",
+            "  |      ^\n",
+            "  = hint: a struct, union or array may be missing its closing delimiter\n",
+        ));
+    }
+
+    #[test]
+    fn test_render_all() {
+        let source = "[\n    1 2,\n    nul\n]";
+        let (_, errors) = deserialize_partial(source);
+
+        assert_eq!(errors.render(source), concat!(
+            "error[E0004]: unexpected token '2'\n",
+            "  --> 2:7\n",
+            "  |\n",
+            "2 |     1 2,\n",
+            "  |       ^\n",
+            "\n",
+            "error[E0009]: invalid keyword 'nul'\n",
+            "  --> 3:5\n",
+            "  |\n",
+            "3 |     nul\n",
+            "  |     ^^^\n",
+            "  = hint: the only keywords are null, true and false, strings must be enclosed in quotation marks\n",
+        ));
+    }
+}
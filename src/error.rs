@@ -1,25 +1,196 @@
-use std::fmt::Display;
+use std::{fmt::Display, io};
 
-use crate::deserializer::tokens::Token;
+use crate::deserializer::{span::{Position, Span}, tokens::TokenKind};
 
 pub type Result<T> = std::result::Result<T, AonError>;
 
+// Every error produced while reading AON text carries the span of the source
+// it refers to. `Custom` errors come from serde, which has no notion of where
+// a value was written, `Io` errors from reading or writing the text itself,
+// and the path errors from editing a `Document`, so they are the only ones
+// without a span.
 #[derive(Debug, Clone, PartialEq)]
 pub enum AonError {
-    UnexpectedCharacter(char),
-    InvalidNumber(String),
-    UnexpectedEndOfFile,
-    UnexpectedToken(Token)
+    UnexpectedCharacter(char, Span),
+    InvalidNumber(String, Span),
+    UnexpectedEndOfFile(Span),
+    UnexpectedToken(TokenKind<'static>, Span),
+    InvalidKey(String, Span),
+    KeyCollision(String, Span),
+    InvalidEscape(String, Span),
+    InvalidKeyword(String, Span),
+    Custom(String),
+    // The kind and message of an `io::Error`, which can be neither cloned
+    // nor compared.
+    Io(io::ErrorKind, String),
+    // A limit set in the `ParseOptions` was exceeded, along with the limit.
+    DepthLimitExceeded(usize, Span),
+    SizeLimitExceeded(usize, Span),
+    StringLimitExceeded(usize, Span),
+    ElementLimitExceeded(usize, Span),
+    // A key written a second time, along with where it was first.
+    DuplicateKey(String, Span, Position),
+    // A `Document` path that is empty where it cannot be, leads nowhere, or
+    // leads somewhere the edit does not apply to.
+    InvalidPath(String),
+    PathNotFound(String),
+    PathExists(String),
+    // The path and the length of the array or tuple it indexes into.
+    IndexOutOfBounds(String, usize),
+    NotAUnion(String),
 }
 
+impl AonError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            AonError::UnexpectedCharacter(_, span)
+            | AonError::InvalidNumber(_, span)
+            | AonError::UnexpectedEndOfFile(span)
+            | AonError::UnexpectedToken(_, span)
+            | AonError::InvalidKey(_, span)
+            | AonError::KeyCollision(_, span)
+            | AonError::InvalidEscape(_, span)
+            | AonError::InvalidKeyword(_, span)
+            | AonError::DepthLimitExceeded(_, span)
+            | AonError::SizeLimitExceeded(_, span)
+            | AonError::StringLimitExceeded(_, span)
+            | AonError::ElementLimitExceeded(_, span)
+            | AonError::DuplicateKey(_, span, _) => Some(*span),
+            AonError::Custom(_)
+            | AonError::Io(..)
+            | AonError::InvalidPath(_)
+            | AonError::PathNotFound(_)
+            | AonError::PathExists(_)
+            | AonError::IndexOutOfBounds(..)
+            | AonError::NotAUnion(_) => None,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            AonError::UnexpectedCharacter(char, _) => format!("unexpected character '{}'", char),
+            AonError::InvalidNumber(number, _) => format!("invalid number format '{}'", number),
+            AonError::UnexpectedEndOfFile(_) => "unexpected end of file".to_owned(),
+            AonError::UnexpectedToken(token, _) => format!("unexpected token '{}'", token),
+            AonError::InvalidKey(key, _) => format!("invalid key '{}'", key),
+            AonError::KeyCollision(key, _) => format!("key '{}' collides with a value that is not a struct", key),
+            AonError::InvalidEscape(escape, _) => format!("invalid escape sequence '{}'", escape),
+            AonError::InvalidKeyword(keyword, _) => format!("invalid keyword '{}'", keyword),
+            AonError::Custom(message) => message.to_owned(),
+            AonError::Io(_, message) => format!("i/o error: {}", message),
+            AonError::DepthLimitExceeded(limit, _) => format!("nesting exceeds the limit of {} levels", limit),
+            AonError::SizeLimitExceeded(limit, _) => format!("input exceeds the limit of {} bytes", limit),
+            AonError::StringLimitExceeded(limit, _) => format!("string exceeds the limit of {} bytes", limit),
+            AonError::ElementLimitExceeded(limit, _) => format!("number of elements exceeds the limit of {}", limit),
+            AonError::DuplicateKey(key, _, first) => format!("duplicate key '{}', first written at {}", key, first),
+            AonError::InvalidPath(path) => format!("expected a path to a key or index, found '{}'", path),
+            AonError::PathNotFound(path) => format!("no value at '{}'", path),
+            AonError::PathExists(path) => format!("'{}' already exists", path),
+            AonError::IndexOutOfBounds(path, length) => format!("index '{}' is out of bounds, the length is {}", path, length),
+            AonError::NotAUnion(path) => format!("'{}' is not a union", path),
+        }
+    }
+}
+
+// Formats as `line:column: message`, so prefixing the file name gives the
+// familiar `config.aon:42:17: unexpected character '}'`.
 impl Display for AonError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AonError::UnexpectedCharacter(char) => write!(f, "Unexpected character: {}", char),
-            AonError::InvalidNumber(number) => write!(f, "Invalid number format: {}", number),
-            AonError::UnexpectedEndOfFile => write!(f, "Unexpected end of file"),
-            AonError::UnexpectedToken(token) => write!(f, "Unexpected token: {}", token),
-            
+        match self.span() {
+            Some(span) => write!(f, "{}: {}", span.start, self.message()),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl std::error::Error for AonError {}
+
+// Every error found in a document by a parser that carries on past them, in
+// the order they appear in it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AonErrors {
+    errors: Vec<AonError>,
+    placeholders: Vec<Span>,
+}
+
+impl AonErrors {
+    pub(crate) fn new(mut errors: Vec<AonError>) -> AonErrors {
+        // Errors without a span refer to the document as a whole.
+        errors.sort_by_key(|error| error.span().map(|span| span.start.offset));
+        AonErrors { errors, placeholders: vec![] }
+    }
+
+    pub(crate) fn with_placeholders(mut self, mut placeholders: Vec<Span>) -> AonErrors {
+        placeholders.sort_by_key(|span| span.start.offset);
+        self.placeholders = placeholders;
+        self
+    }
+
+    // Where the value that was read holds a null in place of one that could
+    // not be parsed, so it can be told apart from a null written as such.
+    // The span is that of the key of a field, of the first token of an
+    // element, or of the first token of the document when nothing of it
+    // could be read.
+    pub fn placeholders(&self) -> &[Span] {
+        &self.placeholders
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, AonError> {
+        self.errors.iter()
+    }
+}
+
+// One error per line.
+impl Display for AonErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, error) in self.errors.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{}", error)?;
         }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for AonErrors {}
+
+impl IntoIterator for AonErrors {
+    type Item = AonError;
+    type IntoIter = std::vec::IntoIter<AonError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a AonErrors {
+    type Item = &'a AonError;
+    type IntoIter = std::slice::Iter<'a, AonError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.iter()
+    }
+}
+
+impl From<io::Error> for AonError {
+    fn from(error: io::Error) -> Self {
+        AonError::Io(error.kind(), error.to_string())
+    }
+}
+
+impl serde::ser::Error for AonError {
+    fn custom<T: Display>(message: T) -> Self {
+        AonError::Custom(message.to_string())
     }
 }
\ No newline at end of file
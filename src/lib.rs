@@ -2,8 +2,10 @@ pub mod representation;
 pub mod serializer;
 pub mod deserializer;
 pub mod error;
+pub mod diagnostic;
 
 pub use representation::*;
+pub use serializer::{to_value, to_string, to_string_pretty, to_writer, lines::AonLinesWriter};
 pub use serde::*;
 pub use deserializer::*;
 pub use error::*;
@@ -24,7 +26,7 @@ mod tests {
             ])),
         ]);
 
-        assert_eq!(value.to_aon(&Formatter::default()), "#person{name:\"John Doe\",age:42,is_cool:true,friends:[\"Jane Doe\",\"Jack Doe\"]}");
+        assert_eq!(value.to_aon(&Formatter::default()), "{name:\"John Doe\",age:42,is_cool:true,friends:[\"Jane Doe\",\"Jack Doe\"]}");
     }
 
     #[test]
@@ -40,7 +42,7 @@ mod tests {
         }
         "#;
 
-        let value = deserializer::deserialize(&aon);
+        let value = deserializer::deserialize(aon);
 
         assert!(value.is_ok());
         println!("{:?}", value)
@@ -0,0 +1,54 @@
+use std::borrow::Cow;
+
+use super::value::{Number, UnionPayload, Value};
+
+// A `Value` whose strings, keys and variant names borrow from the input they
+// were parsed from. Only strings containing escapes need an allocation of
+// their own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedValue<'a> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(Cow<'a, str>),
+    Struct(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+    Union(Cow<'a, str>, BorrowedPayload<'a>),
+    Array(Vec<BorrowedValue<'a>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedPayload<'a> {
+    Unit,
+    Struct(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+    Tuple(Vec<BorrowedValue<'a>>),
+}
+
+impl BorrowedValue<'_> {
+    pub fn into_owned(self) -> Value {
+        match self {
+            BorrowedValue::Null => Value::Null,
+            BorrowedValue::Bool(bool) => Value::Bool(bool),
+            BorrowedValue::Number(number) => Value::Number(number),
+            BorrowedValue::String(string) => Value::String(string.into_owned()),
+            BorrowedValue::Struct(fields) => Value::Struct(into_owned_fields(fields)),
+            BorrowedValue::Union(variant, payload) => Value::Union(variant.into_owned(), payload.into_owned()),
+            BorrowedValue::Array(values) => Value::Array(values.into_iter().map(BorrowedValue::into_owned).collect()),
+        }
+    }
+}
+
+impl BorrowedPayload<'_> {
+    pub fn into_owned(self) -> UnionPayload {
+        match self {
+            BorrowedPayload::Unit => UnionPayload::Unit,
+            BorrowedPayload::Struct(fields) => UnionPayload::Struct(into_owned_fields(fields)),
+            BorrowedPayload::Tuple(values) => UnionPayload::Tuple(values.into_iter().map(BorrowedValue::into_owned).collect()),
+        }
+    }
+}
+
+fn into_owned_fields(fields: Vec<(Cow<'_, str>, BorrowedValue<'_>)>) -> Vec<(String, Value)> {
+    fields.into_iter()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect()
+}
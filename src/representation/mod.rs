@@ -1,2 +1,4 @@
 pub mod value;
-pub use value::*;
\ No newline at end of file
+pub mod borrowed_value;
+pub use value::*;
+pub use borrowed_value::*;
\ No newline at end of file
@@ -7,10 +7,21 @@ pub enum Value {
     Number(Number),
     String(String),
     Struct(Vec<(String, Value)>),
-    Union(String, Vec<(String, Value)>),
+    Union(String, UnionPayload),
     Array(Vec<Value>),
 }
 
+// What follows the variant name of a union. The variant alone, `#none`, and
+// one with an empty struct, `#none {}`, are distinct so that serializing
+// reproduces whichever form was written. Positional values, `#point(1, 2)`,
+// carry payloads that have no field names.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnionPayload {
+    Unit,
+    Struct(Vec<(String, Value)>),
+    Tuple(Vec<Value>),
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -19,13 +30,21 @@ impl Display for Value {
             Value::Number(number) => write!(f, "{}", number),
             Value::String(string) => write!(f, "\"{}\"", string),
             Value::Struct(kvp) => write!(f, "{{{}}}", format_object(kvp)),
-            Value::Union(union, kvp) => write!(f, "#{}:{}", union, format_object(kvp)),
-            Value::Array(_) => todo!(),
+            Value::Union(union, UnionPayload::Unit) => write!(f, "#{}", union),
+            Value::Union(union, UnionPayload::Struct(kvp)) => write!(f, "#{} {{{}}}", union, format_object(kvp)),
+            Value::Union(union, UnionPayload::Tuple(values)) => write!(f, "#{}({})", union, format_values(values)),
+            Value::Array(values) => write!(f, "[{}]", format_values(values)),
         }
     }
 }
 
-fn format_object(key_value_pairs: &Vec<(String, Value)>) -> String {
+fn format_values(values: &[Value]) -> String {
+    values.iter().map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn format_object(key_value_pairs: &[(String, Value)]) -> String {
     key_value_pairs.iter().map(|(name, value)| format!("\"{}\":{}", name, value))
         .collect::<Vec<String>>()
         .join(", ")
@@ -50,26 +69,66 @@ pub enum Number {
 }
 
 impl Number {
+    // Parses `string` into the narrowest fitting representation. Integers
+    // keep their sign classification, so `-0` is a `PosInt`, and floats must
+    // be finite.
     pub fn new(string: &str) -> Option<Self> {
-        match string.parse::<u64>() {
-            Ok(value) => Some(Number::PosInt(value)),
-            Err(_) => match string.parse::<u128>() {
-                Ok(value) => Some(Number::BigPosInt(value)),
-                Err(_) => match string.parse::<i64>() {
-                    Ok(value) => Some(Number::NegInt(value)),
-                    Err(_) => match string.parse::<i128>() {
-                        Ok(value) => Some(Number::BigNegInt(value)),
-                        Err(_) => match string.parse::<f64>() {
-                            Ok(value) => Some(Number::Float(value)),
-                            Err(_) => None,
-                        },
-                    },
-                },
+        if let Ok(value) = string.parse::<u128>() {
+            return Some(Number::from(value));
+        }
+
+        if let Ok(value) = string.parse::<i128>() {
+            return Some(Number::from(value));
+        }
+
+        string.parse::<f64>().ok()
+            .filter(|value| value.is_finite())
+            .map(Number::Float)
+    }
+}
+
+impl From<u64> for Number {
+    fn from(value: u64) -> Self {
+        Number::PosInt(value)
+    }
+}
+
+impl From<u128> for Number {
+    fn from(value: u128) -> Self {
+        match u64::try_from(value) {
+            Ok(value) => Number::PosInt(value),
+            Err(_) => Number::BigPosInt(value),
+        }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(value: i64) -> Self {
+        match u64::try_from(value) {
+            Ok(value) => Number::PosInt(value),
+            Err(_) => Number::NegInt(value),
+        }
+    }
+}
+
+impl From<i128> for Number {
+    fn from(value: i128) -> Self {
+        match u128::try_from(value) {
+            Ok(value) => Number::from(value),
+            Err(_) => match i64::try_from(value) {
+                Ok(value) => Number::NegInt(value),
+                Err(_) => Number::BigNegInt(value),
             },
         }
     }
 }
 
+impl From<f64> for Number {
+    fn from(value: f64) -> Self {
+        Number::Float(value)
+    }
+}
+
 impl Display for Number {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,12 +1,16 @@
-// These are some of the option i want to add to the formatter
-// - New line before '[' for lists and '{' for structs and for unions seperately
-// - New line before # for unions when using standard notation
-// - Space after ':'
-// - Inline list and struct if they are small enough
-// - Inline union if it is small enough
+// How the serializer lays out what it writes. Without options everything goes
+// on one line with no spaces. Lists, structs and unions, the payload of a
+// union, each have their own options for breaking lines:
+// - a new line after the opening delimiter puts each item on a line of its own
+// - items that fit in the inline size stay on one line regardless
+// - a new line before the opening delimiter starts a value broken over lines
+//   on the line after its key
+// - indented items are indented one level deeper than their delimiters
+// - a trailing comma follows the last item on its own line
 
 use std::{collections::{HashMap, HashSet}, fmt::Display};
 
+#[derive(Default)]
 pub struct Formatter {
     options: HashSet<FormatOption>,
 }
@@ -57,14 +61,15 @@ impl Formatter {
     pub fn has_indented(&self, context: FormatContext) -> bool {
         self.options.contains(&FormatOption::Indented(context))
     }
-}
 
-impl Default for Formatter {
-    fn default() -> Self {
-        Self { options: HashSet::default() }
+    // Dot notation is on unless explicitly disabled, as the spec describes
+    // nested single property structs as being wrapped automatically.
+    pub fn has_dot_notation(&self) -> bool {
+        !self.options.contains(&FormatOption::DotNotation(false))
     }
 }
 
+#[derive(Default)]
 pub struct FormatBuilder {
     options: HashMap<String, FormatOption>,
 }
@@ -76,7 +81,10 @@ impl FormatBuilder {
             .space_after_colon()
             .space_after_comma()
             .inline(FormatContext::List, 30)
+            .inline(FormatContext::Union, 30)
             .trailing_comma(FormatContext::All, false)
+            .indented(FormatContext::All)
+            .dot_notation(true)
     }
 
     pub fn json_like() -> Self {
@@ -87,7 +95,9 @@ impl FormatBuilder {
             .space_after_comma()
             .inline(FormatContext::List, 30)
             .trailing_comma(FormatContext::All, false)
+            .indented(FormatContext::All)
             .json_compatible_unions()
+            .dot_notation(false)
     }
 
     pub fn new() -> Self {
@@ -115,12 +125,12 @@ impl FormatBuilder {
     }
 
     pub fn space_after_colon(mut self) -> Self {
-        self.options.insert(format!("{}", stringify!(space_after_colon)), FormatOption::SpaceAfterColon);
+        self.options.insert(stringify!(space_after_colon).to_owned(), FormatOption::SpaceAfterColon);
         self
     }
 
     pub fn space_after_comma(mut self) -> Self {
-        self.options.insert(format!("{}", stringify!(space_after_comma)), FormatOption::SpaceAfterComma);
+        self.options.insert(stringify!(space_after_comma).to_owned(), FormatOption::SpaceAfterComma);
         self
     }
 
@@ -134,8 +144,10 @@ impl FormatBuilder {
         })
     }
 
+    // Writes unions as the JSON objects `deserialize_json` reads them back
+    // from, and every key quoted, so the output is JSON.
     pub fn json_compatible_unions(mut self) -> Self {
-        self.options.insert(format!("{}", stringify!(json_compatible_unions)), FormatOption::JsonCompatibleUnions);
+        self.options.insert(stringify!(json_compatible_unions).to_owned(), FormatOption::JsonCompatibleUnions);
         self
     }
 
@@ -159,6 +171,11 @@ impl FormatBuilder {
         })
     }
 
+    pub fn dot_notation(mut self, dot_notation: bool) -> Self {
+        self.options.insert(stringify!(dot_notation).to_owned(), FormatOption::DotNotation(dot_notation));
+        self
+    }
+
     pub fn build(self) -> Formatter {
         Formatter { options: self.options.values().cloned().collect() }
     }
@@ -185,12 +202,6 @@ impl FormatBuilder {
     }
 }
 
-impl Default for FormatBuilder {
-    fn default() -> Self {
-        Self { options: HashMap::default() }
-    }
-}
-
 #[derive(Clone, PartialEq, Eq, Hash)]
 enum FormatOption {
     NewLineBefore(FormatContext),
@@ -201,6 +212,7 @@ enum FormatOption {
     JsonCompatibleUnions,
     TrailingComma(FormatContext, bool),
     Indented(FormatContext),
+    DotNotation(bool),
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -0,0 +1,105 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::{deserializer::tokens, error::Result};
+
+use super::{formatter::Formatter, to_value, ToAon};
+
+// Writes values as the records of a multi-document stream, which
+// `deserialize_many` reads back. Each record is written in a single call to
+// the writer, so records appended by several writers do not interleave.
+pub struct AonLinesWriter<W> {
+    writer: W,
+    formatter: Formatter,
+    separated: bool,
+}
+
+impl<W: Write> AonLinesWriter<W> {
+    // Writes one record per line.
+    pub fn new(writer: W) -> AonLinesWriter<W> {
+        AonLinesWriter {
+            writer,
+            formatter: Formatter::default(),
+            separated: false,
+        }
+    }
+
+    // Writes records formatted by `formatter`, each followed by a separator
+    // line as they may span several lines.
+    pub fn with_formatter(writer: W, formatter: Formatter) -> AonLinesWriter<W> {
+        AonLinesWriter {
+            writer,
+            formatter,
+            separated: true,
+        }
+    }
+
+    pub fn write<T: ?Sized + ToAon>(&mut self, value: &T) -> Result<()> {
+        let mut record = value.to_aon(&self.formatter);
+        record.push('\n');
+
+        if self.separated {
+            record.push_str(tokens::DOCUMENT_SEPARATOR);
+            record.push('\n');
+        }
+
+        Ok(self.writer.write_all(record.as_bytes())?)
+    }
+
+    pub fn serialize<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.write(&to_value(value)?)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{representation::Value, deserializer::deserialize_many, serializer::formatter::FormatBuilder};
+    use super::*;
+
+    fn records() -> Vec<Value> {
+        let mut record = BTreeMap::new();
+        record.insert("level", vec!["info", "audit"]);
+
+        vec![
+            to_value(&record).unwrap(),
+            to_value(&Some(42)).unwrap(),
+            to_value("multi\nline").unwrap(),
+            Value::Null,
+        ]
+    }
+
+    #[test]
+    fn test_lines() {
+        let mut writer = AonLinesWriter::new(vec![]);
+        records().iter().try_for_each(|record| writer.write(record)).unwrap();
+        writer.serialize(&[1, 2]).unwrap();
+
+        let aon = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(aon, "{level:[\"info\",\"audit\"]}\n#some(42)\n\"multi\\nline\"\nnull\n[1,2]\n");
+
+        let mut expected = records();
+        expected.push(to_value(&[1, 2]).unwrap());
+        assert_eq!(deserialize_many(&aon).collect::<Result<Vec<_>>>(), Ok(expected));
+    }
+
+    #[test]
+    fn test_separated() {
+        let mut writer = AonLinesWriter::with_formatter(vec![], FormatBuilder::sensible_default().build());
+        records().iter().try_for_each(|record| writer.write(record)).unwrap();
+
+        let aon = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(aon, "{\n    level: [\"info\", \"audit\"]\n}\n---\n#some(42)\n---\n\"multi\\nline\"\n---\nnull\n---\n");
+        assert_eq!(deserialize_many(&aon).collect::<Result<Vec<_>>>(), Ok(records()));
+    }
+}
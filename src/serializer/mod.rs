@@ -1,11 +1,35 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, io::Write};
 
-use crate::representation::{Value, value::Number};
+use serde::Serialize;
 
-use self::formatter::Formatter;
+use crate::{representation::{Value, UnionPayload, value::Number}, deserializer::tokens, error::Result};
+
+use self::{formatter::{FormatContext, Formatter}, value_serializer::ValueSerializer};
 
 #[macro_use]
 pub mod formatter;
+pub mod value_serializer;
+pub mod lines;
+
+// One level of indentation, for formatters that indent.
+const INDENT: &str = "    ";
+
+pub fn to_value<T: ?Sized + Serialize>(value: &T) -> Result<Value> {
+    value.serialize(ValueSerializer)
+}
+
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String> {
+    to_string_pretty(value, &Formatter::default())
+}
+
+// Writes `value` spaced, broken over lines and indented as `formatter` says.
+pub fn to_string_pretty<T: ?Sized + Serialize>(value: &T, formatter: &Formatter) -> Result<String> {
+    Ok(to_value(value)?.to_aon(formatter))
+}
+
+pub fn to_writer<W: Write, T: ?Sized + Serialize>(mut writer: W, value: &T) -> Result<()> {
+    Ok(writer.write_all(to_string(value)?.as_bytes())?)
+}
 
 pub trait ToAon {
     fn to_aon(&self, formatter: &Formatter) -> String;
@@ -14,27 +38,27 @@ pub trait ToAon {
 impl<T: ToAon> ToAon for Option<T> {
     fn to_aon(&self, formatter: &Formatter) -> String {
         match self {
-            Some(value) => format!("#some{{{}}}", value.to_aon(formatter)),
-            None => "#none".to_owned(),
+            Some(value) => format_variant("some", Some(value.to_aon(formatter)), formatter),
+            None => format_variant("none", None, formatter),
         }
     }
 }
 
-impl<T: ToAon, U: ToAon> ToAon for Result<T, U> {
+impl<T: ToAon, U: ToAon> ToAon for std::result::Result<T, U> {
     fn to_aon(&self, formatter: &Formatter) -> String {
         match self {
-            Ok(value) => format!("#ok{{{}}}", value.to_aon(formatter)),
-            Err(error) => format!("#err{{{}}}", error.to_aon(formatter)),
+            Ok(value) => format_variant("Ok", Some(value.to_aon(formatter)), formatter),
+            Err(error) => format_variant("Err", Some(error.to_aon(formatter)), formatter),
         }
     }
 }
 
 impl ToAon for Ordering {
-    fn to_aon(&self, _formatter: &Formatter) -> String {
+    fn to_aon(&self, formatter: &Formatter) -> String {
         match self {
-            Ordering::Less => "#less".to_owned(),
-            Ordering::Equal => "#equal".to_owned(),
-            Ordering::Greater => "#greater".to_owned(),
+            Ordering::Less => format_variant("less", None, formatter),
+            Ordering::Equal => format_variant("equal", None, formatter),
+            Ordering::Greater => format_variant("greater", None, formatter),
         }
     }
 }
@@ -80,75 +104,234 @@ impl ToAon for f64 {
 }
 
 impl ToAon for String {
-    fn to_aon(&self, _formatter: &Formatter) -> String {
-        format!("\"{}\"", self)
+    fn to_aon(&self, formatter: &Formatter) -> String {
+        let mut result = String::with_capacity(self.len() + 2);
+
+        result.push('"');
+
+        for c in self.chars() {
+            match c {
+                '"' => result.push_str("\\\""),
+                '\\' => result.push_str("\\\\"),
+                '\n' => result.push_str("\\n"),
+                '\t' => result.push_str("\\t"),
+                '\r' => result.push_str("\\r"),
+                '\u{8}' => result.push_str("\\b"),
+                '\u{c}' => result.push_str("\\f"),
+                // JSON has no short escape for it.
+                '\0' if !formatter.has_json_compatible_unions() => result.push_str("\\0"),
+                c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+                c => result.push(c),
+            }
+        }
+
+        result.push('"');
+
+        result
     }
 }
 
 impl ToAon for Vec<(String, Value)> {
     fn to_aon(&self, formatter: &Formatter) -> String {
-        let mut result = String::new();
+        format_struct(self, formatter, 0)
+    }
+}
+
+impl ToAon for Vec<Value> {
+    fn to_aon(&self, formatter: &Formatter) -> String {
+        format_delimited('[', format_values(self, formatter, 1), ']', FormatContext::List, formatter, 0)
+    }
+}
 
-        result.push('{');
+impl ToAon for (&String, &UnionPayload) {
+    fn to_aon(&self, formatter: &Formatter) -> String {
+        format_union(self.0, self.1, formatter, 0)
+    }
+}
 
-        for (index, (key, value)) in self.iter().enumerate() {
-            if index > 0 {
-                result.push(',');
-            }
+// The value as written `depth` levels deep, which is how far the lines of a
+// value broken over several are indented.
+fn format_value(value: &Value, formatter: &Formatter, depth: usize) -> String {
+    match value {
+        Value::Struct(fields) => format_struct(fields, formatter, depth),
+        Value::Array(values) => format_delimited('[', format_values(values, formatter, depth + 1), ']', FormatContext::List, formatter, depth),
+        Value::Union(name, payload) => format_union(name, payload, formatter, depth),
+        scalar => scalar.to_aon(formatter),
+    }
+}
 
-            result.push_str(&key.to_aon(formatter));
-            result.push(':');
-            result.push_str(&value.to_aon(formatter));
+fn format_struct(fields: &[(String, Value)], formatter: &Formatter, depth: usize) -> String {
+    format_delimited('{', format_fields(fields, FormatContext::Struct, formatter, depth + 1), '}', FormatContext::Struct, formatter, depth)
+}
+
+fn format_union(variant: &String, payload: &UnionPayload, formatter: &Formatter, depth: usize) -> String {
+    if formatter.has_json_compatible_unions() {
+        return format_json_union(variant, payload, formatter, depth);
+    }
+
+    let mut result = String::new();
+
+    result.push('#');
+    result.push_str(&format_name(variant, formatter));
+
+    match payload {
+        UnionPayload::Unit => {}
+        UnionPayload::Struct(fields) => {
+            result.push_str(&format_delimited('{', format_fields(fields, FormatContext::Union, formatter, depth + 1), '}', FormatContext::Union, formatter, depth));
+        }
+        UnionPayload::Tuple(values) => {
+            result.push_str(&format_delimited('(', format_values(values, formatter, depth + 1), ')', FormatContext::Union, formatter, depth));
         }
+    }
+
+    result
+}
 
-        result.push('}');
+// The object a union is written as in JSON, its variant under `type` and its
+// values listed under `values` or named under `fields`. Keeping the payload
+// apart means no field of it can be mistaken for either.
+fn format_json_union(variant: &str, payload: &UnionPayload, formatter: &Formatter, depth: usize) -> String {
+    let mut fields = vec![(tokens::UNION_TYPE.to_owned(), Value::String(variant.to_owned()))];
 
-        result
+    match payload {
+        UnionPayload::Struct(payload) => fields.push((tokens::UNION_FIELDS.to_owned(), Value::Struct(payload.clone()))),
+        UnionPayload::Tuple(values) => fields.push((tokens::UNION_VALUES.to_owned(), Value::Array(values.clone()))),
+        UnionPayload::Unit => {}
     }
+
+    format_delimited('{', format_fields(&fields, FormatContext::Union, formatter, depth + 1), '}', FormatContext::Union, formatter, depth)
 }
 
-impl ToAon for Vec<Value> {
-    fn to_aon(&self, formatter: &Formatter) -> String {
-        let mut result = String::new();
+// A variant of one of the standard unions, with a single value or none.
+fn format_variant(variant: &str, value: Option<String>, formatter: &Formatter) -> String {
+    match (formatter.has_json_compatible_unions(), value) {
+        (false, Some(value)) => format!("#{}({})", variant, value),
+        (false, None) => format!("#{}", variant),
+        (true, value) => {
+            let mut items = vec![format!("{}{}{}", tokens::UNION_TYPE.to_owned().to_aon(formatter), colon(formatter), variant.to_owned().to_aon(formatter))];
+            let values = value.map(|value| format_delimited('[', vec![value], ']', FormatContext::List, formatter, 1));
+            items.extend(values.map(|values| format!("{}{}{}", tokens::UNION_VALUES.to_owned().to_aon(formatter), colon(formatter), values)));
+            format_delimited('{', items, '}', FormatContext::Union, formatter, 0)
+        }
+    }
+}
 
-        result.push('[');
+// Writes the items of a struct, array or union between `open` and `close`.
+// They go on one line unless the formatter breaks lines after the opening
+// delimiter in `context`, and even then when they fit in the inline size.
+fn format_delimited(open: char, items: Vec<String>, close: char, context: FormatContext, formatter: &Formatter, depth: usize) -> String {
+    let separator = match formatter.has_space_after_comma() {
+        true => ", ",
+        false => ",",
+    };
 
-        for (index, value) in self.iter().enumerate() {
-            if index > 0 {
-                result.push(',');
-            }
+    let inline = format!("{}{}{}", open, items.join(separator), close);
+    let fits = |max_size: usize| !inline.contains('\n') && inline.chars().count() <= max_size;
+
+    if items.is_empty() || !formatter.has_new_line_after(context.clone()) || formatter.has_inline(context.clone()).is_some_and(fits) {
+        return inline;
+    }
+
+    let indent = indentation(&context, formatter, depth + 1);
+    let trailing_comma = formatter.has_trailing_comma(context.clone()) == Some(true);
+    let mut result = String::new();
 
-            result.push_str(&value.to_aon(formatter));
+    result.push(open);
+    result.push('\n');
+
+    for (index, item) in items.iter().enumerate() {
+        result.push_str(&indent);
+        result.push_str(item);
+
+        if index + 1 < items.len() || trailing_comma {
+            result.push(',');
         }
 
-        result.push(']');
+        result.push('\n');
+    }
 
-        result
+    result.push_str(&indentation(&context, formatter, depth));
+    result.push(close);
+
+    result
+}
+
+fn indentation(context: &FormatContext, formatter: &Formatter, depth: usize) -> String {
+    match formatter.has_indented(context.clone()) {
+        true => INDENT.repeat(depth),
+        false => String::new(),
     }
 }
 
-impl ToAon for (&String, &Vec<(String, Value)>) {
-    fn to_aon(&self, formatter: &Formatter) -> String {
-        let mut result = String::new();
+fn colon(formatter: &Formatter) -> &'static str {
+    match formatter.has_space_after_colon() {
+        true => ": ",
+        false => ":",
+    }
+}
 
-        result.push('#');
-        result.push_str(&self.0);
-        result.push('{');
+fn format_values(values: &[Value], formatter: &Formatter, depth: usize) -> Vec<String> {
+    values.iter().map(|value| format_value(value, formatter, depth)).collect()
+}
 
-        for (index, (key, value)) in self.1.iter().enumerate() {
-            if index > 0 {
-                result.push(',');
-            }
+// Keys and union variants are written bare when they are valid identifiers
+// and quoted otherwise, or always quoted when the output is meant to be JSON.
+pub(crate) fn format_name(name: &String, formatter: &Formatter) -> String {
+    match tokens::is_identifier(name) && !formatter.has_json_compatible_unions() {
+        true => name.to_owned(),
+        false => name.to_aon(formatter),
+    }
+}
 
-            result.push_str(&key.to_aon(formatter));
-            result.push(':');
-            result.push_str(&value.to_aon(formatter));
+// The fields of a struct, or a union payload as `context` says, written
+// `depth` levels deep.
+fn format_fields(fields: &[(String, Value)], context: FormatContext, formatter: &Formatter, depth: usize) -> Vec<String> {
+    let mut items = vec![];
+
+    for (key, value) in fields {
+        let mut result = format_name(key, formatter);
+
+        // Collapse chains of single property structs into `a.b.c:value`.
+        let mut value = value;
+
+        if formatter.has_dot_notation() && !formatter.has_json_compatible_unions() {
+            while let Value::Struct(nested) = value {
+                match nested.as_slice() {
+                    [(key, inner)] => {
+                        result.push('.');
+                        result.push_str(&format_name(key, formatter));
+                        value = inner;
+                    }
+                    _ => break,
+                }
+            }
         }
 
-        result.push('}');
+        let value_context = match value {
+            Value::Struct(_) => Some(FormatContext::Struct),
+            Value::Array(_) => Some(FormatContext::List),
+            Value::Union(..) => Some(FormatContext::Union),
+            _ => None,
+        };
+
+        let value = format_value(value, formatter, depth);
+
+        // A value broken over several lines may open on the next one, where
+        // the key starts.
+        match value.contains('\n') && value_context.is_some_and(|value_context| formatter.has_new_line_before(value_context)) {
+            true => {
+                result.push(':');
+                result.push('\n');
+                result.push_str(&indentation(&context, formatter, depth));
+            }
+            false => result.push_str(colon(formatter)),
+        }
 
-        result
+        result.push_str(&value);
+        items.push(result);
     }
+
+    items
 }
 
 impl ToAon for Number {
@@ -171,17 +354,16 @@ impl ToAon for Value {
             Value::Bool(value) => value.to_aon(formatter),
             Value::Number(value) => value.to_aon(formatter),
             Value::String(value) => value.to_aon(formatter),
-            Value::Struct(value) => value.to_aon(formatter),
-            Value::Union(name, value) => (name, value).to_aon(formatter),
-            Value::Array(value) => value.to_aon(formatter),
+            Value::Struct(_) | Value::Union(..) | Value::Array(_) => format_value(self, formatter, 0),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::representation::value::Number;
-    use super::*;
+    use crate::{representation::value::Number, deserializer::{deserialize, deserialize_json}, error::AonError};
+    use serde::Serialize;
+    use super::{*, formatter::FormatBuilder};
 
     #[test]
     fn test_null() {
@@ -220,6 +402,15 @@ mod tests {
         assert_eq!(value.to_aon(&Formatter::default()), r#""Hello, World!""#);
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let string = "quote \" backslash \\ lines \n\r\t controls \u{8}\u{c}\0\u{1b} unicode é 😀".to_owned();
+        let aon = string.to_aon(&Formatter::default());
+
+        assert_eq!(aon, r#""quote \" backslash \\ lines \n\r\t controls \b\f\0\u001b unicode é 😀""#);
+        assert_eq!(crate::deserialize(&aon), Ok(Value::String(string)));
+    }
+
     #[test]
     fn test_struct() {
         let value = Value::Struct(vec![
@@ -227,20 +418,244 @@ mod tests {
             ("age".to_owned(), Value::Number(Number::PosInt(42))),
         ]);
 
-        assert_eq!(value.to_aon(&Formatter::default()), r#"{"name":"John Doe","age":42}"#);
+        assert_eq!(value.to_aon(&Formatter::default()), r#"{name:"John Doe",age:42}"#);
     }
 
     #[test]
     fn test_union() {
         let value = Value::Union(
             "person".to_owned(),
-            vec![
+            UnionPayload::Struct(vec![
                 ("name".to_owned(), Value::String("John Doe".to_owned())),
                 ("age".to_owned(), Value::Number(Number::PosInt(42))),
-            ],
+            ]),
         );
 
-        assert_eq!(value.to_aon(&Formatter::default()), r#"#person{"name":"John Doe","age":42}"#);
+        assert_eq!(value.to_aon(&Formatter::default()), r#"#person{name:"John Doe",age:42}"#);
+
+        let value = Value::Union("none".to_owned(), UnionPayload::Struct(vec![]));
+        assert_eq!(value.to_aon(&Formatter::default()), "#none{}");
+
+        let value = Value::Union("none".to_owned(), UnionPayload::Unit);
+        assert_eq!(value.to_aon(&Formatter::default()), "#none");
+    }
+
+    #[test]
+    fn test_quoted_names() {
+        let value = Value::Struct(vec![
+            ("snake_case".to_owned(), Value::Null),
+            ("kebab-case".to_owned(), Value::Null),
+            ("with space".to_owned(), Value::Null),
+            ("1st".to_owned(), Value::Null),
+            ("-dash".to_owned(), Value::Null),
+            ("null".to_owned(), Value::Null),
+            ("".to_owned(), Value::Union("two words".to_owned(), UnionPayload::Unit)),
+        ]);
+
+        let aon = value.to_aon(&Formatter::default());
+
+        assert_eq!(aon, r#"{snake_case:null,kebab-case:null,"with space":null,"1st":null,"-dash":null,"null":null,"":#"two words"}"#);
+        assert_eq!(crate::deserialize(&aon), Ok(value));
+    }
+
+    #[test]
+    fn test_dot_notation() {
+        let value = Value::Struct(vec![
+            ("a".to_owned(), Value::Struct(vec![
+                ("b".to_owned(), Value::Struct(vec![
+                    ("c".to_owned(), Value::Number(Number::PosInt(0))),
+                ])),
+            ])),
+            ("d".to_owned(), Value::Struct(vec![
+                ("e".to_owned(), Value::Null),
+                ("f".to_owned(), Value::Struct(vec![])),
+            ])),
+        ]);
+
+        assert_eq!(value.to_aon(&Formatter::default()), "{a.b.c:0,d:{e:null,f:{}}}");
+
+        let formatter = FormatBuilder::new().dot_notation(false).build();
+        assert_eq!(value.to_aon(&formatter), "{a:{b:{c:0}},d:{e:null,f:{}}}");
+    }
+
+    #[test]
+    fn test_json_compatible_unions() {
+        let formatter = FormatBuilder::new().json_compatible_unions().dot_notation(false).build();
+
+        let value = deserialize(r#"{ a.b: [#Circle { r: 2 }, #some(1), #none, #point(1, 2)], "c d": "\0" }"#).unwrap();
+        let json = value.to_aon(&formatter);
+        assert_eq!(json, r#"{"a":{"b":[{"type":"Circle","fields":{"r":2}},{"type":"some","values":[1]},{"type":"none"},{"type":"point","values":[1,2]}]},"c d":"\u0000"}"#);
+        assert_eq!(deserialize_json(&json), Ok(value));
+
+        let json = Some(Ordering::Less).to_aon(&formatter);
+        assert_eq!(json, r#"{"type":"some","values":[{"type":"less"}]}"#);
+        assert_eq!(deserialize_json(&json), deserialize("#some(#less)"));
+
+        // The payload is kept apart from the variant, so that no field of it
+        // is mistaken for the variant or for positional values. Only a struct
+        // shaped like a union is read back as one.
+        let value = deserialize(r#"[#a { values: [1] }, #b { type: "x", c: 1 }, #c { type: "x" }, #d {}, #e, { type: "f", values: [2] }]"#).unwrap();
+        let json = value.to_aon(&formatter);
+        assert_eq!(json, concat!(
+            r#"[{"type":"a","fields":{"values":[1]}},{"type":"b","fields":{"type":"x","c":1}},{"type":"c","fields":{"type":"x"}},"#,
+            r#"{"type":"d","fields":{}},{"type":"e"},{"type":"f","values":[2]}]"#,
+        ));
+        assert_eq!(deserialize_json(&json), deserialize(r#"[#a { values: [1] }, #b { type: "x", c: 1 }, #c { type: "x" }, #d {}, #e, #f(2)]"#));
+    }
+
+    #[test]
+    fn test_pretty() {
+        let value = deserialize(r#"{ name: "server", ports: [80, 443], mode: #fast, a.b: #tls { cert: "a.pem", key: "server.key" } }"#).unwrap();
+
+        let aon = value.to_aon(&FormatBuilder::sensible_default().build());
+        assert_eq!(aon, concat!(
+            "{\n",
+            "    name: \"server\",\n",
+            "    ports: [80, 443],\n",
+            "    mode: #fast,\n",
+            "    a.b: #tls{\n",
+            "        cert: \"a.pem\",\n",
+            "        key: \"server.key\"\n",
+            "    }\n",
+            "}",
+        ));
+        assert_eq!(deserialize(&aon), Ok(value.clone()));
+
+        let config = std::collections::BTreeMap::from([("hosts", vec!["example.com", "example.org", "example.net"])]);
+        assert_eq!(
+            to_string_pretty(&config, &FormatBuilder::sensible_default().build()).as_deref(),
+            Ok("{\n    hosts: [\n        \"example.com\",\n        \"example.org\",\n        \"example.net\"\n    ]\n}"),
+        );
+
+        let formatter = FormatBuilder::new()
+            .new_line_after(FormatContext::Struct)
+            .new_line_before(FormatContext::Struct)
+            .trailing_comma(FormatContext::Struct, true)
+            .space_after_comma()
+            .build();
+        let aon = Value::Struct(vec![("value".to_owned(), value.clone())]).to_aon(&formatter);
+        assert_eq!(aon, "{\nvalue:\n{\nname:\"server\",\nports:[80, 443],\nmode:#fast,\na.b:#tls{cert:\"a.pem\", key:\"server.key\"},\n},\n}");
+        assert_eq!(deserialize(&aon), Ok(Value::Struct(vec![("value".to_owned(), value.clone())])));
+
+        let json = value.to_aon(&FormatBuilder::json_like().build());
+        assert_eq!(json, concat!(
+            "{\n",
+            "    \"name\": \"server\",\n",
+            "    \"ports\": [80, 443],\n",
+            "    \"mode\":\n",
+            "    {\n",
+            "        \"type\": \"fast\"\n",
+            "    },\n",
+            "    \"a\":\n",
+            "    {\n",
+            "        \"b\":\n",
+            "        {\n",
+            "            \"type\": \"tls\",\n",
+            "            \"fields\":\n",
+            "            {\n",
+            "                \"cert\": \"a.pem\",\n",
+            "                \"key\": \"server.key\"\n",
+            "            }\n",
+            "        }\n",
+            "    }\n",
+            "}",
+        ));
+        assert_eq!(deserialize_json(&json), Ok(value));
+    }
+
+    #[test]
+    fn test_serde() {
+        #[derive(Serialize)]
+        struct Config {
+            name: String,
+            port: u16,
+            offset: i32,
+            ratio: f32,
+            tags: Vec<&'static str>,
+            mode: Mode,
+            fallback: Mode,
+            retry: Option<Retry>,
+            shape: Shape,
+        }
+
+        #[derive(Serialize)]
+        struct Retry {
+            attempts: u8,
+        }
+
+        #[derive(Serialize)]
+        enum Mode {
+            Fast,
+            Custom { level: u8 },
+        }
+
+        #[derive(Serialize)]
+        enum Shape {
+            Circle(u8),
+            Point(i8, i8),
+        }
+
+        let config = Config {
+            name: "server".to_owned(),
+            port: 8080,
+            offset: -2,
+            ratio: 0.5,
+            tags: vec!["a", "b"],
+            mode: Mode::Fast,
+            fallback: Mode::Custom { level: 3 },
+            retry: None,
+            shape: Shape::Circle(4),
+        };
+
+        assert_eq!(
+            to_string(&config),
+            Ok(concat!(
+                r#"{name:"server",port:8080,offset:-2,ratio:0.5,tags:["a","b"],"#,
+                r#"mode:#Fast,fallback:#Custom{level:3},retry:#none,shape:#Circle(4)}"#,
+            ).to_owned()),
+        );
+
+        assert_eq!(to_string(&Shape::Point(1, -1)), Ok("#Point(1,-1)".to_owned()));
+        assert_eq!(to_string(&Some(Retry { attempts: 3 })), Ok("#some({attempts:3})".to_owned()));
+        assert_eq!(to_string(&f64::NAN), Err(AonError::Custom("invalid number 'NaN', numbers must be finite".to_owned())));
+
+        let mut buffer = vec![];
+        assert_eq!(to_writer(&mut buffer, &vec![1, 2]), Ok(()));
+        assert_eq!(buffer, b"[1,2]");
+    }
+
+    #[test]
+    fn test_result() {
+        let formatter = Formatter::default();
+
+        for result in [Ok(5), Err("failed".to_owned())] {
+            let aon = result.to_aon(&formatter);
+
+            assert_eq!(to_string(&result), Ok(aon.clone()));
+            assert_eq!(crate::from_str::<std::result::Result<u64, String>>(&aon), Ok(result));
+        }
+
+        assert_eq!(Ok::<u64, String>(5).to_aon(&formatter), "#Ok(5)");
+    }
+
+    #[test]
+    fn test_writer() {
+        struct Full;
+
+        impl Write for Full {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::StorageFull, "disk full"))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut buffer = vec![];
+        assert_eq!(to_writer(&mut buffer, &[1, 2]), Ok(()));
+        assert_eq!(buffer, b"[1,2]");
+        assert_eq!(to_writer(Full, &[1, 2]), Err(AonError::Io(std::io::ErrorKind::StorageFull, "disk full".to_owned())));
     }
 
     #[test]
@@ -0,0 +1,307 @@
+use serde::{ser, Serialize};
+
+use crate::{
+    representation::{Value, UnionPayload, value::Number},
+    error::{AonError, Result},
+};
+
+// Serializes any `Serialize` type into a `Value` tree, which is then turned
+// into text by `ToAon`.
+//
+// Rust enums map onto unions: unit variants become `#variant`, struct
+// variants keep their fields and newtype and tuple variants carry their
+// values positionally, `#variant(a, b)`. Options are written like any other
+// enum, `#none` and `#some(value)`.
+pub struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = AonError;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> {
+        Ok(Value::Number(Number::from(v)))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value> {
+        Ok(Value::Number(Number::from(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        Ok(Value::Number(Number::from(v)))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value> {
+        Ok(Value::Number(Number::from(v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value> {
+        if v.is_finite() {
+            Ok(Value::Number(Number::from(v)))
+        } else {
+            Err(ser::Error::custom(format_args!("invalid number '{}', numbers must be finite", v)))
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        Ok(Value::Array(v.iter().map(|byte| Value::Number(Number::from(u64::from(*byte)))).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Union(NONE.to_owned(), UnionPayload::Unit))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value> {
+        Ok(Value::Union(SOME.to_owned(), UnionPayload::Tuple(vec![value.serialize(self)?])))
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Value> {
+        Ok(Value::Union(variant.to_owned(), UnionPayload::Unit))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        Ok(Value::Union(variant.to_owned(), UnionPayload::Tuple(vec![value.serialize(self)?])))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray> {
+        Ok(SerializeArray { values: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeTupleVariant> {
+        Ok(SerializeTupleVariant { variant: variant.to_owned(), values: Vec::with_capacity(len) })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap> {
+        Ok(SerializeMap { fields: Vec::with_capacity(len.unwrap_or(0)), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeStruct> {
+        Ok(SerializeStruct { fields: Vec::with_capacity(len) })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeStructVariant> {
+        Ok(SerializeStructVariant { variant: variant.to_owned(), fields: Vec::with_capacity(len) })
+    }
+}
+
+pub(crate) const NONE: &str = "none";
+pub(crate) const SOME: &str = "some";
+
+pub struct SerializeArray {
+    values: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Value;
+    type Error = AonError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Array(self.values))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Value;
+    type Error = AonError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Value;
+    type Error = AonError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeTupleVariant {
+    variant: String,
+    values: Vec<Value>,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = Value;
+    type Error = AonError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Union(self.variant, UnionPayload::Tuple(self.values)))
+    }
+}
+
+pub struct SerializeMap {
+    fields: Vec<(String, Value)>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = AonError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        // Keys are always strings in AON, so scalar keys are converted to
+        // their textual representation.
+        let key = match key.serialize(ValueSerializer)? {
+            Value::String(key) => key,
+            Value::Number(number) => number.to_string(),
+            Value::Bool(bool) => bool.to_string(),
+            other => return Err(ser::Error::custom(format_args!("invalid key '{}', keys must be scalars", other))),
+        };
+
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self.key.take().ok_or_else(|| AonError::Custom("serialize_value called before serialize_key".to_owned()))?;
+        self.fields.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Struct(self.fields))
+    }
+}
+
+pub struct SerializeStruct {
+    fields: Vec<(String, Value)>,
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Ok = Value;
+    type Error = AonError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.fields.push((key.to_owned(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Struct(self.fields))
+    }
+}
+
+pub struct SerializeStructVariant {
+    variant: String,
+    fields: Vec<(String, Value)>,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = Value;
+    type Error = AonError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.fields.push((key.to_owned(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Union(self.variant, UnionPayload::Struct(self.fields)))
+    }
+}